@@ -0,0 +1,62 @@
+//! Frame rendering benchmarks.
+//!
+//! Run with `cargo bench`. `uncached` clears the line raster cache before every
+//! frame, which is what each frame cost before the cache existed.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use ratatui::{buffer::Buffer, layout::Rect, widgets::StatefulWidget};
+use std::hint::black_box;
+use tui_prompter::{
+    App, Teleprompter,
+    config::{Config, FontScale, FontSize},
+};
+
+const SCRIPT: &str = "Good evening and welcome to the program.
+Tonight we look back at a year of remarkable stories,
+from the smallest towns to the biggest cities.
+Our first guest has travelled a long way to be here.";
+
+fn script_app(scale: FontSize) -> App {
+    let mut config = Config::default();
+    config.display.font_scale = FontScale::Fixed(scale);
+    let content = vec![SCRIPT; 50].join("\n");
+    App::new(content, &config).unwrap()
+}
+
+fn frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+
+    for (width, height) in [(120, 40), (240, 60)] {
+        let area = Rect::new(0, 0, width, height);
+        let size = format!("{width}x{height}");
+
+        for scale in [FontSize::Quadrant, FontSize::Full] {
+            let id = format!("{scale}/{size}");
+
+            let mut app = script_app(scale);
+            let mut buf = Buffer::empty(area);
+            group.bench_function(BenchmarkId::new("cached", &id), |b| {
+                b.iter(|| {
+                    app.scroll_offset = (app.scroll_offset + 0.25) % 100.0;
+                    Teleprompter.render(area, &mut buf, &mut app);
+                    black_box(&buf);
+                })
+            });
+
+            let mut app = script_app(scale);
+            group.bench_function(BenchmarkId::new("uncached", &id), |b| {
+                b.iter(|| {
+                    app.scroll_offset = (app.scroll_offset + 0.25) % 100.0;
+                    app.raster_cache.clear();
+                    Teleprompter.render(area, &mut buf, &mut app);
+                    black_box(&buf);
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, frame);
+criterion_main!(benches);
@@ -3,7 +3,12 @@ use std::env;
 use std::fs;
 use std::path::PathBuf;
 
+#[path = "src/options.rs"]
+#[allow(dead_code)] // only the value names are needed here
+mod options;
+
 #[path = "src/cli.rs"]
+#[allow(dead_code)] // only the clap definitions are needed here
 mod cli;
 
 fn main() {
@@ -26,5 +31,6 @@ fn main() {
     fs::copy(&man_path, project_man_dir.join("tp.1")).unwrap();
 
     println!("cargo:rerun-if-changed=src/cli.rs");
+    println!("cargo:rerun-if-changed=src/options.rs");
     println!("cargo:rerun-if-changed=build.rs");
 }
@@ -1,52 +1,249 @@
 //! Application state and logic for the teleprompter.
 
-use crate::config::{Config, parse_color};
+use crate::clock::{Clock, MonotonicClock};
+use crate::config::{Align, Config, DisplayConfig, FontScale, FontSize, OtherLines, parse_color};
+use crate::raster::LineCache;
+use crate::wrap::WrappedLines;
+use anyhow::Result;
 use ratatui::style::Color;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-/// Wraps text to fit within a given character width, preserving words when possible.
-fn wrap_text(text: &str, max_chars: usize) -> Vec<String> {
-    if max_chars == 0 {
-        return vec![];
+/// How a line is presented relative to the main prompt text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LineKind {
+    /// Regular prompt text
+    #[default]
+    Normal,
+    /// Context-only text, such as another character's dialogue
+    Dimmed,
+    /// Context-only text drawn one font scale smaller
+    Reduced,
+    /// Section marker drawn as a divider, such as a scene heading
+    Marker,
+}
+
+impl LineKind {
+    /// Returns the kind used for lines outside the selected character or speaker,
+    /// or `None` if they should be hidden.
+    pub fn for_others(others: OtherLines) -> Option<Self> {
+        match others {
+            OtherLines::Dim => Some(LineKind::Dimmed),
+            OtherLines::Smaller => Some(LineKind::Reduced),
+            OtherLines::Hide => None,
+        }
     }
+}
 
-    let mut lines = Vec::new();
-    let mut current_line = String::new();
+/// A single line of prompt text together with how it should be shown.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ScriptLine {
+    pub text: String,
+    pub kind: LineKind,
+    /// Text color override, e.g. the speaker's color
+    pub color: Option<Color>,
+}
 
-    for word in text.split_whitespace() {
-        if word.len() > max_chars {
-            if !current_line.is_empty() {
-                lines.push(current_line);
-                current_line = String::new();
-            }
-            let mut remaining = word;
-            while remaining.len() > max_chars {
-                lines.push(remaining[..max_chars].to_string());
-                remaining = &remaining[max_chars..];
-            }
-            if !remaining.is_empty() {
-                current_line = remaining.to_string();
+impl ScriptLine {
+    pub fn new(text: impl Into<String>, kind: LineKind) -> Self {
+        Self {
+            text: text.into(),
+            kind,
+            color: None,
+        }
+    }
+
+    pub fn with_color(mut self, color: Option<Color>) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+/// Splits a `NAME: text` speaker prefix off a line.
+///
+/// Names are one to three words made of letters, e.g. `ALICE:` or `Dr. Smith:`.
+fn split_speaker(line: &str) -> Option<(&str, &str)> {
+    let (name, text) = line.split_once(':')?;
+    let name = name.trim();
+    let words = name.split_whitespace().count();
+
+    let is_name = (1..=3).contains(&words)
+        && name.starts_with(char::is_alphabetic)
+        && name
+            .chars()
+            .all(|c| c.is_alphabetic() || matches!(c, ' ' | '.' | '\'' | '-'));
+
+    // Requiring a space after the colon skips things like URLs and times
+    if is_name && (text.is_empty() || text.starts_with(char::is_whitespace)) {
+        Some((name, text.trim()))
+    } else {
+        None
+    }
+}
+
+/// Parses a color setting that `Config::check_colors` has already accepted.
+fn checked_color(value: Option<&str>) -> Option<Color> {
+    value.map(|color| parse_color(color).expect("colors are checked first"))
+}
+
+/// Returns the speakers of a `NAME: line` script in order of first
+/// appearance, or none if the text doesn't look like one.
+///
+/// It takes at least two speakers, and either two of them speaking more than
+/// once or every paragraph opening with a turn, so that a stray `Note:` in
+/// prose doesn't turn a plain script into a one-line turn and a monologue.
+fn detect_speakers(content: &str) -> Vec<String> {
+    let mut turns: Vec<(String, usize)> = Vec::new();
+    let (mut paragraphs, mut opened_by_turns) = (0, 0);
+    let mut previous_blank = true;
+
+    for line in content.lines() {
+        let blank = line.trim().is_empty();
+        let speaker = split_speaker(line).map(|(name, _)| name);
+        if previous_blank && !blank {
+            paragraphs += 1;
+            opened_by_turns += usize::from(speaker.is_some());
+        }
+        previous_blank = blank;
+
+        if let Some(name) = speaker {
+            match turns.iter_mut().find(|(s, _)| s.eq_ignore_ascii_case(name)) {
+                Some((_, count)) => *count += 1,
+                None => turns.push((name.to_string(), 1)),
             }
-        } else if current_line.is_empty() {
-            current_line = word.to_string();
-        } else if current_line.len() + 1 + word.len() <= max_chars {
-            current_line.push(' ');
-            current_line.push_str(word);
-        } else {
-            lines.push(current_line);
-            current_line = word.to_string();
         }
     }
 
-    if !current_line.is_empty() {
-        lines.push(current_line);
+    let recurring = turns.iter().filter(|(_, count)| *count > 1).count();
+    if turns.len() < 2 || (recurring < 2 && opened_by_turns < paragraphs) {
+        return Vec::new();
+    }
+    turns.into_iter().map(|(name, _)| name).collect()
+}
+
+/// Builds script lines from plain text, detecting `NAME: line` speaker turns.
+///
+/// A turn continues on following lines until the next speaker prefix. Returns
+/// the lines together with the detected speakers in order of first appearance.
+fn script_lines(content: &str, config: &Config) -> (Vec<ScriptLine>, Vec<String>) {
+    let speakers = detect_speakers(content);
+    if speakers.is_empty() {
+        let lines = content
+            .lines()
+            .map(|line| ScriptLine::new(line, LineKind::Normal))
+            .collect();
+        return (lines, Vec::new());
     }
 
-    if lines.is_empty() && !text.is_empty() {
-        lines.push(String::new());
+    let selected = config.script.speaker.as_deref();
+    let other_kind = LineKind::for_others(config.script.others);
+    let speaker_color = |name: &str| {
+        config
+            .speakers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .and_then(|(_, color)| checked_color(Some(color.as_str())))
+    };
+
+    let mut lines: Vec<ScriptLine> = Vec::new();
+    let mut current: Option<&str> = None;
+
+    for line in content.lines() {
+        if let Some((name, _)) = split_speaker(line) {
+            current = Some(name);
+        }
+
+        if line.trim().is_empty() {
+            if lines.last().is_some_and(|l| !l.text.is_empty()) {
+                lines.push(ScriptLine::default());
+            }
+            continue;
+        }
+
+        let kind = match (selected, current) {
+            (None, _) => Some(LineKind::Normal),
+            (Some(wanted), Some(name)) if wanted.eq_ignore_ascii_case(name) => {
+                Some(LineKind::Normal)
+            }
+            _ => other_kind,
+        };
+
+        if let Some(kind) = kind {
+            let color = current.and_then(speaker_color);
+            lines.push(ScriptLine::new(line, kind).with_color(color));
+        }
     }
 
-    lines
+    (lines, speakers)
+}
+
+/// Characters a line must fit with `font_scale = "auto"` if neither
+/// `min_line_chars` nor `min_line_words` is set.
+const DEFAULT_MIN_LINE_CHARS: usize = 12;
+
+/// Horizontal paddings, in percent, the padding key cycles through.
+const PADDING_STEPS: [u16; 5] = [0, 10, 20, 30, 40];
+
+/// The layout text was last wrapped for. The text is rewrapped, and the raster
+/// cache dropped, whenever any part of it changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WrapKey {
+    /// Width of the whole text area, before padding
+    pub width: u16,
+    /// Font scale actually used, which may be smaller than `App::font_scale`
+    pub scale: FontSize,
+    /// Horizontal padding as percentage of the width
+    pub padding: u16,
+}
+
+/// Returns how many characters a line must fit for automatic font sizing:
+/// `min_line_chars`, or `min_line_words` words of the script's average word
+/// length, whichever is more.
+fn min_line_chars(display: &DisplayConfig, lines: &[ScriptLine]) -> usize {
+    let chars = match (display.min_line_chars, display.min_line_words) {
+        (Some(chars), _) => chars,
+        (None, Some(_)) => 0,
+        (None, None) => DEFAULT_MIN_LINE_CHARS,
+    };
+    let words = display.min_line_words.map_or(0, |words| {
+        let (letters, count) = lines
+            .iter()
+            .flat_map(|line| line.text.split_whitespace())
+            .fold((0, 0), |(letters, count), word| {
+                (letters + word.chars().count(), count + 1)
+            });
+        let average = if count == 0 {
+            5.0
+        } else {
+            letters as f64 / count as f64
+        };
+        // Words are separated by single spaces
+        (words as f64 * (average + 1.0) - 1.0).ceil() as usize
+    });
+    chars.max(words)
+}
+
+/// Where a script sits in a playlist of several.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentPosition {
+    /// Zero-based index of the segment
+    pub index: usize,
+    /// Number of segments in the playlist
+    pub count: usize,
+}
+
+/// A move to another segment of a playlist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentChange {
+    Next,
+    Previous,
+}
+
+/// Which settings were changed with keys while prompting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Adjustments {
+    pub speed: bool,
+    pub font_scale: bool,
+    pub padding: bool,
 }
 
 /// Main application state for the teleprompter.
@@ -54,116 +251,331 @@ fn wrap_text(text: &str, max_chars: usize) -> Vec<String> {
 /// Holds the text content, scroll position, display settings, and runtime state.
 pub struct App {
     /// Original lines from input text
-    pub lines: Vec<String>,
-    /// Lines wrapped to fit current terminal width
-    pub wrapped_lines: Vec<String>,
+    pub lines: Vec<ScriptLine>,
+    /// Speakers detected in a `NAME: line` script, in order of appearance
+    pub speakers: Vec<String>,
+    /// Lines wrapped to fit current terminal width, computed on demand
+    pub wrapped: WrappedLines,
     /// Current scroll position (fractional for smooth scrolling)
     pub scroll_offset: f64,
     /// Whether scrolling is paused
     pub paused: bool,
     /// Scroll speed in lines per second
     pub speed: f64,
-    /// Font scale (1=small, 2=medium, 3=large)
-    pub font_scale: u8,
+    /// How long the whole script should take to scroll through. While set, the
+    /// speed is worked out from it whenever the layout changes
+    pub target_duration: Option<Duration>,
+    /// Script title shown in the status bar
+    pub title: Option<String>,
+    /// The script's place in a playlist, if it's one segment of several
+    pub segment: Option<SegmentPosition>,
+    /// Font size of the big text
+    pub font_scale: FontSize,
+    /// With an `auto` font size, the characters a line must fit; the UI then
+    /// picks `font_scale` from the available width
+    pub auto_fit: Option<usize>,
     pub text_color: Color,
     pub background_color: Color,
+    /// Color of section marker labels, if not the text color
+    pub highlight_color: Option<Color>,
+    /// Color of the rules either side of section markers, if not the text color
+    pub guide_color: Option<Color>,
+    /// Color of the status bar, if not dimmed text
+    pub status_color: Option<Color>,
+    /// Color of dimmed context lines, if not dimmed text
+    pub dimmed_color: Option<Color>,
     /// Horizontal padding as percentage of screen width
     pub horizontal_padding: u16,
+    /// Settings changed by hand, which carry over to the next segment
+    pub adjusted: Adjustments,
+    pub align: Align,
+    /// Blank rows between lines
+    pub line_spacing: u16,
+    /// Extra blank rows at paragraph breaks
+    pub paragraph_spacing: u16,
     pub last_update: Instant,
+    /// Time source for scrolling and pause handling
+    pub clock: Box<dyn Clock>,
     pub should_quit: bool,
+    /// Set when the user asks to suspend (Ctrl-Z); the host handles it and clears it
+    pub should_suspend: bool,
+    /// Set when the user asks to save the settings; the host saves them and
+    /// clears it
+    pub should_save: bool,
+    /// Set when the app should move to another segment, at the end of one or
+    /// when the user asks; the host loads that segment in its place
+    pub segment_change: Option<SegmentChange>,
+    /// Message shown in the status bar in place of the key help until the next
+    /// key press
+    pub notice: Option<String>,
+    /// Message drawn over the text until the given time, such as a config
+    /// error
+    pub overlay: Option<(String, Instant)>,
     /// Number of visible lines (updated by UI on each render)
     pub visible_height: usize,
-    /// Last terminal width, used to detect when rewrap is needed
-    pub last_width: u16,
+    /// Rows of text in each line at the rendered scale, without spacing
+    /// (updated by UI on each render)
+    pub line_height: u16,
+    /// Layout of the last wrap, used to detect when rewrap is needed
+    pub wrap_key: Option<WrapKey>,
+    /// Rasterized wrapped lines, cleared whenever the text is rewrapped
+    pub raster_cache: LineCache,
 }
 
 impl App {
     /// Creates a new App with the given text content and configuration.
-    pub fn new(content: String, config: &Config) -> Self {
-        let lines: Vec<String> = content.lines().map(String::from).collect();
+    ///
+    /// Multi-speaker scripts are colored from the `[speakers]` table and filtered
+    /// down to `script.speaker` if one is selected.
+    ///
+    /// Fails if a color in the config doesn't parse (see `Config::check_colors`).
+    pub fn new(content: String, config: &Config) -> Result<Self> {
+        config.check_colors()?;
+        let (lines, speakers) = script_lines(&content, config);
+        Ok(Self {
+            speakers,
+            ..Self::from_lines(lines, config)?
+        })
+    }
 
-        Self {
+    /// Creates a new App from pre-parsed script lines, e.g. from a Fountain screenplay.
+    ///
+    /// Fails if a color in the config doesn't parse (see `Config::check_colors`).
+    pub fn from_lines(lines: Vec<ScriptLine>, config: &Config) -> Result<Self> {
+        config.check_colors()?;
+        let color = |value: &Option<String>| checked_color(value.as_deref());
+        let (font_scale, auto_fit) = match config.display.font_scale {
+            FontScale::Fixed(size) => (size, None),
+            FontScale::Auto => (
+                FontSize::default(),
+                Some(min_line_chars(&config.display, &lines)),
+            ),
+        };
+        Ok(Self {
             lines,
-            wrapped_lines: Vec::new(),
+            speakers: Vec::new(),
+            wrapped: WrappedLines::default(),
             scroll_offset: 0.0,
             paused: false,
             speed: config.scroll.speed,
-            font_scale: config.display.font_scale,
-            text_color: parse_color(&config.display.text_color),
-            background_color: parse_color(&config.display.background_color),
+            target_duration: None,
+            title: None,
+            segment: None,
+            font_scale,
+            auto_fit,
+            text_color: color(&config.display.text_color).unwrap_or(Color::White),
+            background_color: color(&config.display.background_color).unwrap_or(Color::Black),
+            highlight_color: color(&config.display.highlight_color),
+            guide_color: color(&config.display.guide_color),
+            status_color: color(&config.display.status_color),
+            dimmed_color: color(&config.display.dimmed_color),
             horizontal_padding: config.display.horizontal_padding,
+            adjusted: Adjustments::default(),
+            align: config.display.align,
+            line_spacing: config.display.line_spacing,
+            paragraph_spacing: config.display.paragraph_spacing,
             last_update: Instant::now(),
+            clock: Box::new(MonotonicClock),
             should_quit: false,
+            should_suspend: false,
+            should_save: false,
+            segment_change: None,
+            notice: None,
+            overlay: None,
             visible_height: 24,
-            last_width: 0,
+            line_height: crate::ui::get_line_height(font_scale),
+            wrap_key: None,
+            raster_cache: LineCache::default(),
+        })
+    }
+
+    /// Replaces the clock that drives scrolling, e.g. with a `ManualClock`.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.last_update = clock.now();
+        self.clock = Box::new(clock);
+        self
+    }
+
+    fn max_scroll(&mut self) -> f64 {
+        let len = self.wrapped.len(&self.lines);
+        self.lines_before(len) + self.visible_height as f64
+    }
+
+    /// Returns the rows from one line to the next, including line spacing.
+    pub fn row_pitch(&self) -> u64 {
+        (self.line_height + self.line_spacing) as u64
+    }
+
+    /// Returns the row wrapped line `idx` starts at, counting line and
+    /// paragraph spacing. Past the end of the script, lines continue at the
+    /// regular pitch.
+    pub fn line_row(&mut self, idx: usize) -> u64 {
+        let breaks = self.wrapped.blanks_before(&self.lines, idx) as u64;
+        idx as u64 * self.row_pitch() + breaks * self.paragraph_spacing as u64
+    }
+
+    /// Returns the wrapped line that covers `row`, including its spacing.
+    pub fn line_at_row(&mut self, row: u64) -> usize {
+        let pitch = self.row_pitch();
+        // Every line takes at least the pitch, and at most the pitch plus one
+        // paragraph break
+        let mut low = (row / (pitch + self.paragraph_spacing as u64)) as usize;
+        let mut high = (row / pitch) as usize + 1;
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if self.line_row(mid) <= row {
+                low = mid;
+            } else {
+                high = mid;
+            }
         }
+        low
     }
 
-    fn max_scroll(&self) -> f64 {
-        let total_content = if self.wrapped_lines.is_empty() {
-            self.lines.len() as f64
-        } else {
-            self.wrapped_lines.len() as f64
-        };
-        total_content + self.visible_height as f64
+    /// Returns the scroll offset, in lines, at which wrapped line `idx` starts.
+    /// Without paragraph spacing that is just `idx`.
+    fn lines_before(&mut self, idx: usize) -> f64 {
+        self.line_row(idx) as f64 / self.row_pitch() as f64
     }
 
     /// Re-wraps all lines to fit within the given character width.
+    ///
+    /// Lines are wrapped lazily as they are looked up, so this is cheap even for
+    /// very long scripts.
     pub fn update_wrap(&mut self, max_chars: usize) {
-        self.wrapped_lines = self
-            .lines
-            .iter()
-            .flat_map(|line| {
-                if line.trim().is_empty() {
-                    vec![String::new()]
-                } else {
-                    wrap_text(line, max_chars)
-                }
+        self.raster_cache.clear();
+        self.wrapped = WrappedLines::new(max_chars);
+    }
+
+    /// Returns the first and one past the last row of source line `source`.
+    fn source_rows(&mut self, source: usize) -> (f64, f64) {
+        let start = self.wrapped.first_wrapped(&self.lines, source);
+        let end = self.wrapped.first_wrapped(&self.lines, source + 1);
+        (self.line_row(start) as f64, self.line_row(end) as f64)
+    }
+
+    /// Rewraps for a new layout showing `visible_height` lines of
+    /// `line_height` rows, keeping the line at the top of the screen on the
+    /// same source line.
+    ///
+    /// Within that source line the position is kept proportionally, so a
+    /// paragraph that was half read stays half read after the change.
+    pub fn relayout(&mut self, max_chars: usize, visible_height: usize, line_height: u16) {
+        let top = self.scroll_offset - self.visible_height as f64;
+        let top_row = top * self.row_pitch() as f64;
+        let anchor = (top >= 0.0)
+            .then(|| {
+                let idx = self.line_at_row(top_row as u64);
+                self.wrapped.source_line(&self.lines, idx)
             })
-            .collect();
+            .flatten()
+            .map(|source| {
+                let (start, end) = self.source_rows(source);
+                (source, (top_row - start) / (end - start).max(1.0))
+            });
+
+        self.update_wrap(max_chars);
+        self.visible_height = visible_height;
+        self.line_height = line_height;
+
+        let top = match anchor {
+            Some((source, fraction)) => {
+                let (start, end) = self.source_rows(source);
+                (start + fraction * (end - start)) / self.row_pitch() as f64
+            }
+            // Text that hasn't reached the top yet keeps its distance from it
+            None => top,
+        };
+        self.scroll_offset = (top + visible_height as f64).max(0.0);
+    }
+
+    /// Sets the speed so that the whole script, from the first line entering
+    /// the screen to the last one leaving it, takes `target_duration`. Call it
+    /// whenever the layout changes.
+    pub fn pace_to_target(&mut self) {
+        if let Some(duration) = self.target_duration.filter(|d| !d.is_zero()) {
+            self.speed = self.max_scroll() / duration.as_secs_f64();
+        }
+    }
+
+    /// Returns wrapped line `idx`, or `None` past the end of the script.
+    pub fn wrapped_line(&mut self, idx: usize) -> Option<&ScriptLine> {
+        self.wrapped.get(&self.lines, idx)
+    }
+
+    /// Returns how wrapped line `idx` is aligned. With `justify`, the last line
+    /// of a paragraph is left-aligned rather than stretched.
+    pub fn line_align(&mut self, idx: usize) -> Align {
+        if self.align == Align::Justify
+            && self.wrapped.source_line(&self.lines, idx + 1)
+                != self.wrapped.source_line(&self.lines, idx)
+        {
+            return Align::Left;
+        }
+        self.align
+    }
+
+    /// Returns the total number of wrapped lines.
+    pub fn wrapped_len(&mut self) -> usize {
+        self.wrapped.len(&self.lines)
     }
 
     /// Advances the scroll position based on elapsed time since last update.
-    /// Quits automatically when the last line scrolls out of view.
+    /// Quits automatically when the last line scrolls out of view, or moves on
+    /// to the next segment of a playlist.
     pub fn update(&mut self) {
         if self.paused {
-            self.last_update = Instant::now();
+            self.last_update = self.clock.now();
             return;
         }
 
-        let now = Instant::now();
+        let now = self.clock.now();
         let elapsed = now.duration_since(self.last_update).as_secs_f64();
         self.last_update = now;
 
         self.scroll_offset += self.speed * elapsed;
 
-        let total_lines = if self.wrapped_lines.is_empty() {
-            self.lines.len()
-        } else {
-            self.wrapped_lines.len()
-        };
-
-        // Quit when the last line has scrolled out of view
-        if self.scroll_offset >= total_lines as f64 + self.visible_height as f64 {
-            self.should_quit = true;
+        // Quit when the last line has scrolled out of view. Only the lines up
+        // to the top of the screen need to be counted to find out; spacing only
+        // ever pushes lines further down.
+        let past_top = (self.scroll_offset - self.visible_height as f64).max(0.0);
+        let known_lines = self.wrapped.len_up_to(&self.lines, past_top as usize + 1);
+        if self.scroll_offset >= self.lines_before(known_lines) + self.visible_height as f64 {
+            match self.segment {
+                Some(segment) if segment.index + 1 < segment.count => {
+                    self.segment_change = Some(SegmentChange::Next)
+                }
+                _ => self.should_quit = true,
+            }
         }
     }
 
+    /// Forgets the time since the last update, so that time spent suspended or
+    /// otherwise not updating doesn't make the text jump ahead.
+    pub fn skip_elapsed(&mut self) {
+        self.last_update = self.clock.now();
+    }
+
     /// Toggles between paused and playing states.
     pub fn toggle_pause(&mut self) {
         self.paused = !self.paused;
         if !self.paused {
-            self.last_update = Instant::now();
+            self.last_update = self.clock.now();
         }
     }
 
-    /// Increases scroll speed by 0.5 lines/second (max 20).
+    /// Increases scroll speed by 0.5 lines/second (max 20). Changing the
+    /// speed by hand stops pacing to the target duration.
     pub fn speed_up(&mut self) {
+        self.adjusted.speed = true;
+        self.target_duration = None;
         self.speed = (self.speed + 0.5).min(20.0);
     }
 
     /// Decreases scroll speed by 0.5 lines/second (min 0.5).
     pub fn speed_down(&mut self) {
+        self.adjusted.speed = true;
+        self.target_duration = None;
         self.speed = (self.speed - 0.5).max(0.5);
     }
 
@@ -178,9 +590,639 @@ impl App {
         self.scroll_offset = (self.scroll_offset + 1.0).min(max);
     }
 
+    /// Scrolls to where the last line is at the bottom of the screen.
+    pub fn scroll_to_end(&mut self) {
+        let len = self.wrapped.len(&self.lines);
+        self.scroll_offset = self.lines_before(len);
+    }
+
+    /// Switches to the next larger font scale, wrapping around to the smallest.
+    /// Picking a size by hand turns off automatic sizing.
+    pub fn cycle_font_scale(&mut self) {
+        self.adjusted.font_scale = true;
+        self.auto_fit = None;
+        self.font_scale = self.font_scale.larger().unwrap_or(FontSize::ALL[0]);
+    }
+
+    /// Switches to the next smaller font scale, wrapping around to the largest.
+    pub fn cycle_font_scale_back(&mut self) {
+        self.adjusted.font_scale = true;
+        self.auto_fit = None;
+        self.font_scale = self
+            .font_scale
+            .smaller()
+            .unwrap_or(FontSize::ALL[FontSize::ALL.len() - 1]);
+    }
+
+    /// Switches to the next larger padding step, wrapping around to none.
+    pub fn cycle_padding(&mut self) {
+        self.adjusted.padding = true;
+        self.horizontal_padding = PADDING_STEPS
+            .into_iter()
+            .find(|&step| step > self.horizontal_padding)
+            .unwrap_or(PADDING_STEPS[0]);
+    }
+
+    /// Takes over the speed, font size and padding `previous` had adjusted by
+    /// hand, such as when moving on to the next segment of a playlist.
+    pub fn keep_adjustments(&mut self, previous: &App) {
+        let adjusted = previous.adjusted;
+        if adjusted.speed {
+            self.speed = previous.speed;
+            self.target_duration = None;
+        }
+        if adjusted.font_scale {
+            self.font_scale = previous.font_scale;
+            self.auto_fit = None;
+        }
+        if adjusted.padding {
+            self.horizontal_padding = previous.horizontal_padding;
+        }
+        self.adjusted = adjusted;
+    }
+
+    /// Applies the display and scroll settings that differ between `old` and
+    /// `new`, such as after the config file was edited. Settings that didn't
+    /// change keep any adjustment made while prompting.
+    ///
+    /// Fails without changing anything if a color in `new` doesn't parse.
+    pub fn reconfigure(&mut self, old: &Config, new: &Config) -> Result<()> {
+        new.check_colors()?;
+        let color = |value: &Option<String>| checked_color(value.as_deref());
+        let (was, display) = (&old.display, &new.display);
+
+        if display.font_scale != was.font_scale
+            || display.min_line_chars != was.min_line_chars
+            || display.min_line_words != was.min_line_words
+        {
+            match display.font_scale {
+                FontScale::Fixed(size) => {
+                    self.font_scale = size;
+                    self.auto_fit = None;
+                }
+                FontScale::Auto => self.auto_fit = Some(min_line_chars(display, &self.lines)),
+            }
+        }
+        if display.text_color != was.text_color {
+            self.text_color = color(&display.text_color).unwrap_or(Color::White);
+        }
+        if display.background_color != was.background_color {
+            self.background_color = color(&display.background_color).unwrap_or(Color::Black);
+        }
+        for (color_now, value, value_was) in [
+            (
+                &mut self.highlight_color,
+                &display.highlight_color,
+                &was.highlight_color,
+            ),
+            (
+                &mut self.guide_color,
+                &display.guide_color,
+                &was.guide_color,
+            ),
+            (
+                &mut self.status_color,
+                &display.status_color,
+                &was.status_color,
+            ),
+            (
+                &mut self.dimmed_color,
+                &display.dimmed_color,
+                &was.dimmed_color,
+            ),
+        ] {
+            if value != value_was {
+                *color_now = color(value);
+            }
+        }
+        if display.horizontal_padding != was.horizontal_padding {
+            self.horizontal_padding = display.horizontal_padding;
+        }
+        if display.align != was.align {
+            self.align = display.align;
+        }
+        if display.line_spacing != was.line_spacing
+            || display.paragraph_spacing != was.paragraph_spacing
+        {
+            self.line_spacing = display.line_spacing;
+            self.paragraph_spacing = display.paragraph_spacing;
+            // Lines move to different rows, so lay them out again
+            self.wrap_key = None;
+        }
+        // A target duration still sets the speed
+        if new.scroll.speed != old.scroll.speed && self.target_duration.is_none() {
+            self.speed = new.scroll.speed;
+        }
+
+        // Colors other than the text and background aren't part of the cache key
+        self.raster_cache.clear();
+        Ok(())
+    }
+
+    /// Shows `message` over the text for `duration`.
+    pub fn show_overlay(&mut self, message: impl Into<String>, duration: Duration) {
+        self.overlay = Some((message.into(), self.clock.now() + duration));
+    }
+
+    /// Removes the overlay once its time is up, returning whether it did so
+    /// and the screen needs redrawing.
+    pub fn expire_overlay(&mut self) -> bool {
+        let expired = self
+            .overlay
+            .as_ref()
+            .is_some_and(|(_, until)| self.clock.now() >= *until);
+        if expired {
+            self.overlay = None;
+        }
+        expired
+    }
+
     /// Resets scroll position to the beginning.
     pub fn reset(&mut self) {
         self.scroll_offset = 0.0;
-        self.last_update = Instant::now();
+        self.last_update = self.clock.now();
+    }
+
+    /// Asks for the next segment of a playlist, if there is one.
+    pub fn next_segment(&mut self) {
+        match self.segment {
+            Some(segment) if segment.index + 1 < segment.count => {
+                self.segment_change = Some(SegmentChange::Next)
+            }
+            Some(_) => self.notice = Some("Last segment".to_string()),
+            None => {}
+        }
+    }
+
+    /// Asks for the previous segment of a playlist, or starts the first one
+    /// over.
+    pub fn previous_segment(&mut self) {
+        match self.segment {
+            Some(segment) if segment.index > 0 => {
+                self.segment_change = Some(SegmentChange::Previous)
+            }
+            Some(_) => self.reset(),
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::time::Duration;
+
+    /// Builds an app with `lines` one-word lines, a 5-line viewport and a manual clock.
+    fn test_app(lines: usize, speed: f64) -> (App, ManualClock) {
+        let content = (1..=lines)
+            .map(|i| format!("line{i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut config = Config::default();
+        config.scroll.speed = speed;
+
+        let clock = ManualClock::new();
+        let mut app = App::new(content, &config)
+            .unwrap()
+            .with_clock(clock.clone());
+        app.visible_height = 5;
+        (app, clock)
+    }
+
+    #[test]
+    fn update_scrolls_at_configured_speed() {
+        let (mut app, clock) = test_app(100, 2.0);
+
+        clock.advance(Duration::from_secs(1));
+        app.update();
+        assert_eq!(app.scroll_offset, 2.0);
+
+        clock.advance(Duration::from_millis(500));
+        app.update();
+        assert_eq!(app.scroll_offset, 3.0);
+    }
+
+    #[test]
+    fn update_without_elapsed_time_does_not_scroll() {
+        let (mut app, _clock) = test_app(100, 2.0);
+
+        app.update();
+        app.update();
+        assert_eq!(app.scroll_offset, 0.0);
+    }
+
+    #[test]
+    fn speed_change_applies_to_following_updates() {
+        let (mut app, clock) = test_app(100, 2.0);
+
+        clock.advance(Duration::from_secs(1));
+        app.update();
+        app.speed_up();
+        clock.advance(Duration::from_secs(2));
+        app.update();
+        assert_eq!(app.scroll_offset, 2.0 + 2.5 * 2.0);
+    }
+
+    #[test]
+    fn speed_is_clamped() {
+        let (mut app, _clock) = test_app(10, 19.5);
+        app.speed_up();
+        app.speed_up();
+        assert_eq!(app.speed, 20.0);
+
+        let (mut app, _clock) = test_app(10, 1.0);
+        app.speed_down();
+        app.speed_down();
+        assert_eq!(app.speed, 0.5);
+    }
+
+    #[test]
+    fn paused_app_does_not_scroll() {
+        let (mut app, clock) = test_app(100, 2.0);
+
+        app.toggle_pause();
+        clock.advance(Duration::from_secs(10));
+        app.update();
+        assert_eq!(app.scroll_offset, 0.0);
+    }
+
+    #[test]
+    fn time_spent_paused_is_not_caught_up_on_resume() {
+        let (mut app, clock) = test_app(100, 2.0);
+
+        app.toggle_pause();
+        clock.advance(Duration::from_secs(10));
+        app.toggle_pause();
+        clock.advance(Duration::from_secs(1));
+        app.update();
+        assert_eq!(app.scroll_offset, 2.0);
+    }
+
+    #[test]
+    fn speaker_prefixes_are_names_followed_by_a_space() {
+        assert_eq!(split_speaker("ALICE: Hello"), Some(("ALICE", "Hello")));
+        assert_eq!(
+            split_speaker("Dr. Smith:  Yes. "),
+            Some(("Dr. Smith", "Yes."))
+        );
+        assert_eq!(split_speaker("BOB:"), Some(("BOB", "")));
+        for line in [
+            "https://example.com",
+            "Meet at 10:30",
+            "One two three four: too many words",
+            "R2D2: digits",
+            "ALICE:no space",
+        ] {
+            assert_eq!(split_speaker(line), None, "{line}");
+        }
+    }
+
+    #[test]
+    fn speakers_are_detected_only_in_scripts_of_turns() {
+        for script in [
+            "ALICE: Hi\nBOB: Hello",
+            "ALICE: Hi\n\nBOB: Hello\nstill Bob",
+            "Intro prose.\n\nALICE: One\n\nBOB: Two\n\nAnd then.\n\nALICE: Three\n\nBOB: Four",
+        ] {
+            assert_eq!(detect_speakers(script), ["ALICE", "BOB"], "{script}");
+        }
+        for script in [
+            "Plain text\nwith lines",
+            "ALICE: Talking to herself",
+            "Note: read slowly\n\nOnce upon a time.\n\nALICE: Hello\n\nALICE: Goodbye",
+        ] {
+            assert!(detect_speakers(script).is_empty(), "{script}");
+        }
+    }
+
+    #[test]
+    fn speaker_turns_are_colored_and_filtered() {
+        let script = "ALICE: Hi\nhow are you\n\nBOB: Fine\n\nalice: Good";
+        let mut config = Config::default();
+        config
+            .speakers
+            .insert("Alice".to_string(), "green".to_string());
+
+        let app = App::new(script.to_string(), &config).unwrap();
+        assert_eq!(app.speakers, ["ALICE", "BOB"]);
+        let colors: Vec<_> = app.lines.iter().map(|line| line.color).collect();
+        assert_eq!(
+            colors,
+            [
+                Some(Color::Green),
+                Some(Color::Green),
+                None,
+                None,
+                None,
+                Some(Color::Green)
+            ]
+        );
+
+        config.script.speaker = Some("bob".to_string());
+        config.script.others = OtherLines::Hide;
+        let app = App::new(script.to_string(), &config).unwrap();
+        let texts: Vec<_> = app.lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, ["BOB: Fine", ""]);
+    }
+
+    #[test]
+    fn quits_once_last_line_leaves_the_screen() {
+        let (mut app, clock) = test_app(10, 1.0);
+
+        // 10 lines plus a 5-line viewport: still visible just before 15
+        clock.advance(Duration::from_millis(14_900));
+        app.update();
+        assert!(!app.should_quit);
+
+        clock.advance(Duration::from_millis(100));
+        app.update();
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn segments_move_on_instead_of_quitting() {
+        let (mut app, clock) = test_app(10, 1.0);
+        app.segment = Some(SegmentPosition { index: 0, count: 2 });
+
+        app.previous_segment();
+        assert_eq!(app.segment_change, None);
+        clock.advance(Duration::from_secs(15));
+        app.update();
+        assert!(!app.should_quit);
+        assert_eq!(app.segment_change, Some(SegmentChange::Next));
+
+        // The last segment quits at its end, and can't skip ahead
+        let (mut app, clock) = test_app(10, 1.0);
+        app.segment = Some(SegmentPosition { index: 1, count: 2 });
+        app.next_segment();
+        assert_eq!(app.segment_change, None);
+        assert_eq!(app.notice.as_deref(), Some("Last segment"));
+        app.previous_segment();
+        assert_eq!(app.segment_change, Some(SegmentChange::Previous));
+        clock.advance(Duration::from_secs(15));
+        app.update();
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn adjustments_carry_over_to_the_next_segment() {
+        let (mut app, _clock) = test_app(10, 2.0);
+        app.speed_up();
+        app.cycle_padding();
+
+        let (mut next, _clock) = test_app(10, 4.0);
+        next.target_duration = Some(Duration::from_secs(30));
+        next.font_scale = FontSize::Full;
+        next.horizontal_padding = 5;
+        next.keep_adjustments(&app);
+        assert_eq!(next.speed, 2.5);
+        assert_eq!(next.target_duration, None);
+        assert_eq!(next.horizontal_padding, app.horizontal_padding);
+        // Not adjusted, so the next segment's own size stays
+        assert_eq!(next.font_scale, FontSize::Full);
+        assert_eq!(next.adjusted, app.adjusted);
+    }
+
+    #[test]
+    fn quit_threshold_uses_wrapped_lines() {
+        let (mut app, clock) = test_app(2, 1.0);
+        app.lines = vec![ScriptLine::new("one two three four", LineKind::Normal)];
+        app.update_wrap(5);
+        assert_eq!(app.wrapped_len(), 4);
+
+        clock.advance(Duration::from_millis(8_500));
+        app.update();
+        assert!(!app.should_quit);
+
+        clock.advance(Duration::from_millis(500));
+        app.update();
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn max_scroll_counts_source_lines_before_first_wrap() {
+        let (mut app, _clock) = test_app(10, 1.0);
+        assert_eq!(app.wrapped.max_chars(), None);
+        assert_eq!(app.max_scroll(), 15.0);
+    }
+
+    #[test]
+    fn max_scroll_counts_wrapped_lines() {
+        let (mut app, _clock) = test_app(10, 1.0);
+        app.update_wrap(3);
+        assert_eq!(app.wrapped_len(), 20);
+        assert_eq!(app.max_scroll(), 25.0);
+    }
+
+    #[test]
+    fn max_scroll_of_empty_script_is_the_viewport() {
+        let (mut app, _clock) = test_app(0, 1.0);
+        app.update_wrap(10);
+        assert_eq!(app.max_scroll(), 5.0);
+    }
+
+    #[test]
+    fn spacing_is_counted_in_rows() {
+        let (mut app, _clock) = test_app(2, 1.0);
+        app.lines = ["one", "two", "", "three"]
+            .map(|text| ScriptLine::new(text, LineKind::Normal))
+            .to_vec();
+        app.update_wrap(10);
+        app.line_height = 4;
+        app.line_spacing = 1;
+        app.paragraph_spacing = 5;
+
+        // Lines are 5 rows apart, and the blank line is 5 rows taller
+        assert_eq!(app.line_row(2), 10);
+        assert_eq!(app.line_row(3), 20);
+        assert_eq!(app.line_at_row(19), 2);
+        assert_eq!(app.line_at_row(20), 3);
+
+        // Four lines plus a paragraph break of one more line, and the viewport
+        assert_eq!(app.max_scroll(), 4.0 + 1.0 + 5.0);
+        app.scroll_to_end();
+        assert_eq!(app.scroll_offset, 5.0);
+    }
+
+    #[test]
+    fn justified_paragraphs_end_left_aligned() {
+        let (mut app, _clock) = test_app(2, 1.0);
+        app.lines = vec![ScriptLine::new("aa bb cc", LineKind::Normal)];
+        app.update_wrap(5);
+        app.align = Align::Justify;
+
+        assert_eq!(app.line_align(0), Align::Justify);
+        assert_eq!(app.line_align(1), Align::Left);
+    }
+
+    #[test]
+    fn manual_scrolling_is_clamped() {
+        let (mut app, _clock) = test_app(3, 1.0);
+
+        app.scroll_up();
+        assert_eq!(app.scroll_offset, 0.0);
+
+        for _ in 0..20 {
+            app.scroll_down();
+        }
+        let max = app.max_scroll();
+        assert_eq!(app.scroll_offset, max);
+        assert_eq!(app.scroll_offset, 8.0);
+    }
+
+    #[test]
+    fn reset_returns_to_start_without_a_time_jump() {
+        let (mut app, clock) = test_app(100, 2.0);
+
+        clock.advance(Duration::from_secs(5));
+        app.update();
+        app.reset();
+        assert_eq!(app.scroll_offset, 0.0);
+
+        app.update();
+        assert_eq!(app.scroll_offset, 0.0);
+    }
+
+    #[test]
+    fn relayout_keeps_the_top_line_on_the_same_source_line() {
+        let (mut app, _clock) = test_app(2, 1.0);
+        app.lines = (0..10)
+            .map(|i| ScriptLine::new(format!("aa bb cc dd {i}"), LineKind::Normal))
+            .collect();
+
+        // Five wrapped lines per source line; the top is half way into line 3
+        app.update_wrap(2);
+        app.scroll_offset = 17.5 + 5.0;
+
+        // Now three wrapped lines per source line, and a taller viewport
+        app.relayout(5, 8, app.line_height);
+        assert_eq!(app.visible_height, 8);
+        assert_eq!(app.wrapped.source_line(&app.lines, 10), Some(3));
+        assert_eq!(app.scroll_offset, 10.5 + 8.0);
+    }
+
+    #[test]
+    fn relayout_before_the_text_reaches_the_top_keeps_the_gap() {
+        let (mut app, _clock) = test_app(10, 1.0);
+        app.update_wrap(10);
+        app.scroll_offset = 2.0;
+
+        app.relayout(10, 3, app.line_height);
+        assert_eq!(app.scroll_offset, 0.0);
+    }
+
+    #[test]
+    fn target_duration_sets_the_speed() {
+        let (mut app, clock) = test_app(10, 1.0);
+        app.target_duration = Some(Duration::from_secs(30));
+
+        // Ten lines plus a screen of five to scroll them off, in 30 seconds
+        app.relayout(10, 5, app.line_height);
+        app.pace_to_target();
+        assert_eq!(app.speed, 0.5);
+        clock.advance(Duration::from_secs(29));
+        app.update();
+        assert!(!app.should_quit);
+        clock.advance(Duration::from_secs(1));
+        app.update();
+        assert!(app.should_quit);
+
+        app.speed_up();
+        app.relayout(10, 3, app.line_height);
+        app.pace_to_target();
+        assert_eq!(app.speed, 1.0);
+    }
+
+    #[test]
+    fn reconfigure_applies_only_changed_settings() {
+        let (mut app, clock) = test_app(10, 2.0);
+        let old = Config::default();
+        let mut new = old.clone();
+        new.display.text_color = Some("green".to_string());
+        new.display.horizontal_padding = 30;
+
+        // Adjusted while prompting
+        app.speed_up();
+        app.cycle_font_scale();
+        app.reconfigure(&old, &new).unwrap();
+        assert_eq!(app.text_color, Color::Green);
+        assert_eq!(app.horizontal_padding, 30);
+        assert_eq!(app.speed, 2.5);
+        assert_eq!(app.font_scale, FontSize::HalfWidth);
+
+        let mut newer = new.clone();
+        newer.scroll.speed = 4.0;
+        newer.display.font_scale = FontScale::Fixed(FontSize::Quadrant);
+        app.reconfigure(&new, &newer).unwrap();
+        assert_eq!(app.speed, 4.0);
+        assert_eq!(app.font_scale, FontSize::Quadrant);
+
+        app.show_overlay("bad config", Duration::from_secs(5));
+        clock.advance(Duration::from_secs(4));
+        assert!(!app.expire_overlay());
+        clock.advance(Duration::from_secs(1));
+        assert!(app.expire_overlay());
+        assert!(app.overlay.is_none());
+    }
+
+    #[test]
+    fn colors_that_dont_parse_are_errors() {
+        let mut config = Config::default();
+        config
+            .speakers
+            .insert("ALICE".to_string(), "blu".to_string());
+        assert!(App::new("ALICE: hi\nBOB: hello".to_string(), &config).is_err());
+
+        let mut config = Config::default();
+        config.display.text_color = Some("gren".to_string());
+        assert!(App::from_lines(Vec::new(), &config).is_err());
+
+        // Nothing changes when the new config is rejected
+        let (mut app, _clock) = test_app(1, 1.0);
+        config.display.horizontal_padding = 30;
+        assert!(app.reconfigure(&Config::default(), &config).is_err());
+        assert_eq!(app.text_color, Color::White);
+        assert_ne!(app.horizontal_padding, 30);
+    }
+
+    #[test]
+    fn font_scale_and_padding_cycle() {
+        let (mut app, _clock) = test_app(1, 1.0);
+        app.font_scale = FontSize::Double;
+        app.horizontal_padding = 15;
+
+        app.cycle_font_scale();
+        assert_eq!(app.font_scale, FontSize::Triple);
+        app.cycle_font_scale();
+        assert_eq!(app.font_scale, FontSize::Sextant);
+        app.cycle_font_scale_back();
+        assert_eq!(app.font_scale, FontSize::Triple);
+
+        app.cycle_padding();
+        assert_eq!(app.horizontal_padding, 20);
+        for _ in 0..2 {
+            app.cycle_padding();
+        }
+        assert_eq!(app.horizontal_padding, 40);
+        app.cycle_padding();
+        assert_eq!(app.horizontal_padding, 0);
+    }
+
+    #[test]
+    fn auto_fit_needs_enough_characters_for_the_words() {
+        let mut config = Config::default();
+        config.display.font_scale = FontScale::Auto;
+        let app = App::new("aaa bbb ccc".to_string(), &config).unwrap();
+        assert_eq!(app.auto_fit, Some(DEFAULT_MIN_LINE_CHARS));
+
+        // Four 3-letter words and the spaces between them
+        config.display.min_line_words = Some(4);
+        let app = App::new("aaa bbb ccc".to_string(), &config).unwrap();
+        assert_eq!(app.auto_fit, Some(15));
+
+        config.display.min_line_chars = Some(20);
+        let app = App::new("aaa bbb ccc".to_string(), &config).unwrap();
+        assert_eq!(app.auto_fit, Some(20));
     }
 }
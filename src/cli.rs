@@ -1,34 +1,227 @@
 //! Command-line argument parsing.
 
-use clap::Parser;
+use crate::options::{ExportFormat, FontScale, OtherLines};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
+use std::str::FromStr;
 
 /// A terminal-based teleprompter application.
 #[derive(Parser, Debug)]
 #[command(name = "tp")]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    /// Path to a text file to display
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Text files to display one after another, or a playlist (.m3u or .toml)
     #[arg(value_name = "FILE")]
-    pub file: Option<PathBuf>,
+    pub files: Vec<PathBuf>,
 
     /// Scroll speed (lines per second)
-    #[arg(short, long, value_name = "SPEED")]
+    #[arg(short, long, value_name = "SPEED", global = true)]
     pub speed: Option<f64>,
 
-    /// Font scale factor (1-3)
-    #[arg(short = 'S', long, value_name = "SCALE")]
-    pub scale: Option<u8>,
+    /// Font size, from smallest to largest (1-3 are quadrant, half-height and full), or auto
+    #[arg(short = 'S', long, value_name = "SIZE", global = true)]
+    pub scale: Option<FontScale>,
+
+    /// Read settings from this config file instead of the default one
+    #[arg(
+        long,
+        value_name = "PATH",
+        env = "TP_CONFIG",
+        hide_env_values = true,
+        global = true
+    )]
+    pub config: Option<PathBuf>,
 
-    /// Text color (e.g., white, green, #FF0000)
-    #[arg(short, long, value_name = "COLOR")]
+    /// Use the settings in the config file's [profiles.<NAME>] section
+    #[arg(
+        short = 'P',
+        long,
+        value_name = "NAME",
+        env = "TP_PROFILE",
+        hide_env_values = true,
+        global = true
+    )]
+    pub profile: Option<String>,
+
+    /// Color theme: classic, high-contrast, amber, solarized, or a theme file
+    #[arg(short, long, value_name = "THEME", global = true)]
+    pub theme: Option<String>,
+
+    /// Text color (e.g., green, orange, #FF0000, rgb(0,128,255), 208)
+    #[arg(short, long, value_name = "COLOR", global = true)]
     pub color: Option<String>,
 
-    /// Background color (e.g., black, blue, #000033)
-    #[arg(short, long, value_name = "COLOR")]
+    /// Background color (e.g., black, navy, #003)
+    #[arg(short, long, value_name = "COLOR", global = true)]
     pub background: Option<String>,
 
     /// Horizontal padding as percentage of screen width (0-40)
-    #[arg(short, long, value_name = "PERCENT")]
+    #[arg(short, long, value_name = "PERCENT", global = true)]
     pub padding: Option<u16>,
+
+    /// Parse the input as a Fountain screenplay (implied for .fountain files)
+    #[arg(short = 'f', long, global = true)]
+    pub fountain: bool,
+
+    /// Prompt only this character's dialogue (Fountain scripts)
+    #[arg(short = 'C', long, value_name = "NAME", global = true)]
+    pub character: Option<String>,
+
+    /// Prompt only this speaker's lines in a `NAME: line` script
+    #[arg(long, value_name = "NAME", global = true)]
+    pub speaker: Option<String>,
+
+    /// How to show lines that don't belong to the selected character or speaker
+    #[arg(short, long, value_name = "MODE", global = true)]
+    pub others: Option<OtherLines>,
+
+    /// Show the prompter in a strip of this many rows below the prompt
+    /// instead of taking over the whole terminal
+    #[arg(long, value_name = "ROWS", value_parser = clap::value_parser!(u16).range(5..))]
+    pub inline: Option<u16>,
+
+    /// Save the speed, font size, padding and colors to the config file (or
+    /// the profile in use) when quitting
+    #[arg(long)]
+    pub save_on_exit: bool,
+
+    /// Maximum frames per second while scrolling
+    #[arg(long, value_name = "FPS", default_value_t = 60, value_parser = clap::value_parser!(u32).range(1..))]
+    pub fps: u32,
+
+    /// Print the text as big letters to stdout instead of scrolling it
+    /// [default format: ansi on a terminal, text otherwise]
+    #[arg(long, value_name = "FORMAT", require_equals = true, num_args = 0..=1)]
+    pub print: Option<Option<RenderFormat>>,
+
+    /// Output width in columns for --print [default: terminal width]
+    #[arg(long, value_name = "COLUMNS", requires = "print")]
+    pub width: Option<u16>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Render a single frame to stdout instead of starting the prompter
+    Render(RenderArgs),
+    /// Record the scrolling prompter as an asciicast or animated GIF
+    Export(ExportArgs),
+    /// Create, inspect or check the config file
+    Config(ConfigArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub action: ConfigCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigCommand {
+    /// Print the path of the config file
+    Path,
+    /// Write a config file with every default setting, commented
+    Init {
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print the effective config and where each value came from
+    Show {
+        /// Include the front matter of this script
+        #[arg(value_name = "FILE")]
+        file: Option<PathBuf>,
+    },
+    /// Check the config file, command-line settings and a script's front
+    /// matter for errors
+    Check {
+        /// Also check the front matter of this script
+        #[arg(value_name = "FILE")]
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+pub struct RenderArgs {
+    /// Path to a text file to render
+    #[arg(value_name = "FILE")]
+    pub file: Option<PathBuf>,
+
+    /// Scroll offset in lines (at 0 the text starts just below the screen)
+    #[arg(long, value_name = "OFFSET", default_value_t = 0.0)]
+    pub at: f64,
+
+    /// Frame size in columns and rows
+    #[arg(long, value_name = "WIDTHxHEIGHT", default_value = "80x24")]
+    pub size: Size,
+
+    /// Output format
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    pub format: RenderFormat,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ExportArgs {
+    /// Path to a text file to record
+    #[arg(value_name = "FILE")]
+    pub file: Option<PathBuf>,
+
+    /// Recording format
+    #[arg(long, value_name = "FORMAT", default_value = "cast")]
+    pub format: ExportFormat,
+
+    /// Output file, or - for stdout [default: FILE with the format's extension]
+    #[arg(long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// Terminal size in columns and rows
+    #[arg(long, value_name = "WIDTHxHEIGHT", default_value = "80x24")]
+    pub size: Size,
+
+    /// Frames per second of simulated playback
+    #[arg(long, value_name = "FPS", default_value_t = 20, value_parser = clap::value_parser!(u32).range(1..))]
+    pub fps: u32,
+
+    /// Overwrite an existing recording named after FILE
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// Output format for rendered frames.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum RenderFormat {
+    /// Plain text, without colors
+    Text,
+    /// Text with ANSI color escapes
+    Ansi,
+}
+
+/// A terminal size given as `WIDTHxHEIGHT`, e.g. `120x40`.
+#[derive(Clone, Copy, Debug)]
+pub struct Size {
+    pub width: u16,
+    pub height: u16,
+}
+
+impl FromStr for Size {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s
+            .split_once(['x', 'X'])
+            .ok_or_else(|| format!("expected WIDTHxHEIGHT, got '{}'", s))?;
+        let parse = |n: &str| {
+            n.trim()
+                .parse::<u16>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| format!("invalid size '{}'", s))
+        };
+        Ok(Size {
+            width: parse(width)?,
+            height: parse(height)?,
+        })
+    }
 }
@@ -0,0 +1,500 @@
+//! Color parsing and downsampling to the terminal's color depth.
+//!
+//! Colors can be given as one of the 16 ANSI color names, a CSS color name,
+//! `#RGB` or `#RRGGBB`, `rgb(r, g, b)`, `hsl(h, s%, l%)` or an ANSI 256-color
+//! index. Typos are errors, with a suggestion where a name is close.
+
+use ratatui::buffer::Buffer;
+use ratatui::style::Color;
+
+/// The 16 ANSI colors, which follow the terminal's own palette.
+const ANSI_NAMES: [(&str, Color); 18] = [
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("grey", Color::Gray),
+    ("darkgray", Color::DarkGray),
+    ("darkgrey", Color::DarkGray),
+    ("lightred", Color::LightRed),
+    ("lightgreen", Color::LightGreen),
+    ("lightyellow", Color::LightYellow),
+    ("lightblue", Color::LightBlue),
+    ("lightmagenta", Color::LightMagenta),
+    ("lightcyan", Color::LightCyan),
+    ("white", Color::White),
+];
+
+/// CSS color names not shadowed by an ANSI name.
+const CSS_NAMES: [(&str, u32); 132] = [
+    ("aliceblue", 0xF0F8FF),
+    ("antiquewhite", 0xFAEBD7),
+    ("aqua", 0x00FFFF),
+    ("aquamarine", 0x7FFFD4),
+    ("azure", 0xF0FFFF),
+    ("beige", 0xF5F5DC),
+    ("bisque", 0xFFE4C4),
+    ("blanchedalmond", 0xFFEBCD),
+    ("blueviolet", 0x8A2BE2),
+    ("brown", 0xA52A2A),
+    ("burlywood", 0xDEB887),
+    ("cadetblue", 0x5F9EA0),
+    ("chartreuse", 0x7FFF00),
+    ("chocolate", 0xD2691E),
+    ("coral", 0xFF7F50),
+    ("cornflowerblue", 0x6495ED),
+    ("cornsilk", 0xFFF8DC),
+    ("crimson", 0xDC143C),
+    ("darkblue", 0x00008B),
+    ("darkcyan", 0x008B8B),
+    ("darkgoldenrod", 0xB8860B),
+    ("darkgreen", 0x006400),
+    ("darkkhaki", 0xBDB76B),
+    ("darkmagenta", 0x8B008B),
+    ("darkolivegreen", 0x556B2F),
+    ("darkorange", 0xFF8C00),
+    ("darkorchid", 0x9932CC),
+    ("darkred", 0x8B0000),
+    ("darksalmon", 0xE9967A),
+    ("darkseagreen", 0x8FBC8F),
+    ("darkslateblue", 0x483D8B),
+    ("darkslategray", 0x2F4F4F),
+    ("darkslategrey", 0x2F4F4F),
+    ("darkturquoise", 0x00CED1),
+    ("darkviolet", 0x9400D3),
+    ("deeppink", 0xFF1493),
+    ("deepskyblue", 0x00BFFF),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1E90FF),
+    ("firebrick", 0xB22222),
+    ("floralwhite", 0xFFFAF0),
+    ("forestgreen", 0x228B22),
+    ("fuchsia", 0xFF00FF),
+    ("gainsboro", 0xDCDCDC),
+    ("ghostwhite", 0xF8F8FF),
+    ("gold", 0xFFD700),
+    ("goldenrod", 0xDAA520),
+    ("greenyellow", 0xADFF2F),
+    ("honeydew", 0xF0FFF0),
+    ("hotpink", 0xFF69B4),
+    ("indianred", 0xCD5C5C),
+    ("indigo", 0x4B0082),
+    ("ivory", 0xFFFFF0),
+    ("khaki", 0xF0E68C),
+    ("lavender", 0xE6E6FA),
+    ("lavenderblush", 0xFFF0F5),
+    ("lawngreen", 0x7CFC00),
+    ("lemonchiffon", 0xFFFACD),
+    ("lightcoral", 0xF08080),
+    ("lightgoldenrodyellow", 0xFAFAD2),
+    ("lightpink", 0xFFB6C1),
+    ("lightsalmon", 0xFFA07A),
+    ("lightseagreen", 0x20B2AA),
+    ("lightskyblue", 0x87CEFA),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xB0C4DE),
+    ("lightgray", 0xD3D3D3),
+    ("lightgrey", 0xD3D3D3),
+    ("lime", 0x00FF00),
+    ("limegreen", 0x32CD32),
+    ("linen", 0xFAF0E6),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66CDAA),
+    ("mediumblue", 0x0000CD),
+    ("mediumorchid", 0xBA55D3),
+    ("mediumpurple", 0x9370DB),
+    ("mediumseagreen", 0x3CB371),
+    ("mediumslateblue", 0x7B68EE),
+    ("mediumspringgreen", 0x00FA9A),
+    ("mediumturquoise", 0x48D1CC),
+    ("mediumvioletred", 0xC71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xF5FFFA),
+    ("mistyrose", 0xFFE4E1),
+    ("moccasin", 0xFFE4B5),
+    ("navajowhite", 0xFFDEAD),
+    ("navy", 0x000080),
+    ("oldlace", 0xFDF5E6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6B8E23),
+    ("orange", 0xFFA500),
+    ("orangered", 0xFF4500),
+    ("orchid", 0xDA70D6),
+    ("palegoldenrod", 0xEEE8AA),
+    ("palegreen", 0x98FB98),
+    ("paleturquoise", 0xAFEEEE),
+    ("palevioletred", 0xDB7093),
+    ("papayawhip", 0xFFEFD5),
+    ("peachpuff", 0xFFDAB9),
+    ("peru", 0xCD853F),
+    ("pink", 0xFFC0CB),
+    ("plum", 0xDDA0DD),
+    ("powderblue", 0xB0E0E6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("rosybrown", 0xBC8F8F),
+    ("royalblue", 0x4169E1),
+    ("saddlebrown", 0x8B4513),
+    ("salmon", 0xFA8072),
+    ("sandybrown", 0xF4A460),
+    ("seagreen", 0x2E8B57),
+    ("seashell", 0xFFF5EE),
+    ("sienna", 0xA0522D),
+    ("silver", 0xC0C0C0),
+    ("skyblue", 0x87CEEB),
+    ("slateblue", 0x6A5ACD),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xFFFAFA),
+    ("springgreen", 0x00FF7F),
+    ("steelblue", 0x4682B4),
+    ("tan", 0xD2B48C),
+    ("teal", 0x008080),
+    ("thistle", 0xD8BFD8),
+    ("tomato", 0xFF6347),
+    ("turquoise", 0x40E0D0),
+    ("violet", 0xEE82EE),
+    ("wheat", 0xF5DEB3),
+    ("whitesmoke", 0xF5F5F5),
+    ("yellowgreen", 0x9ACD32),
+];
+
+/// Parses a color string into a ratatui `Color`.
+///
+/// Names are matched ignoring case, spaces, `-` and `_`, so "Light Blue" and
+/// "light-blue" both work. The 16 ANSI names keep the terminal's own shades;
+/// the other CSS names are exact RGB colors.
+pub fn parse_color(color_str: &str) -> Result<Color, String> {
+    let s = color_str.trim().to_lowercase();
+
+    if let Some(hex) = s.strip_prefix('#') {
+        return parse_hex(hex)
+            .ok_or_else(|| format!("invalid hex color '{color_str}' (expected #RGB or #RRGGBB)"));
+    }
+    if let Some(args) = function_args(&s, "rgb") {
+        return parse_rgb(&args).ok_or_else(|| {
+            format!("invalid color '{color_str}' (expected rgb(0-255, 0-255, 0-255))")
+        });
+    }
+    if let Some(args) = function_args(&s, "hsl") {
+        return parse_hsl(&args).ok_or_else(|| {
+            format!("invalid color '{color_str}' (expected hsl(0-360, 0-100%, 0-100%))")
+        });
+    }
+    if let Ok(index) = s.parse::<u16>() {
+        return u8::try_from(index)
+            .map(Color::Indexed)
+            .map_err(|_| format!("color index {index} is out of range (expected 0-255)"));
+    }
+
+    let name: String = s
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .collect();
+    if let Some((_, color)) = ANSI_NAMES.iter().find(|(ansi, _)| *ansi == name) {
+        return Ok(*color);
+    }
+    if let Some((_, rgb)) = CSS_NAMES.iter().find(|(css, _)| *css == name) {
+        return Ok(Color::from_u32(*rgb));
+    }
+
+    Err(match suggest(&name) {
+        Some(suggestion) => format!("unknown color '{color_str}' (did you mean '{suggestion}'?)"),
+        None => format!(
+            "unknown color '{color_str}' (expected a color name, #RRGGBB, rgb(), hsl() or 0-255)"
+        ),
+    })
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    match hex.len() {
+        // Each digit is doubled: #F80 is #FF8800
+        3 => {
+            let [r, g, b] = [8, 4, 0].map(|shift| ((value >> shift) & 0xF) as u8 * 0x11);
+            Some(Color::Rgb(r, g, b))
+        }
+        6 => Some(Color::from_u32(value)),
+        _ => None,
+    }
+}
+
+/// Returns the comma-separated arguments of `name(...)`.
+fn function_args<'a>(s: &'a str, name: &str) -> Option<Vec<&'a str>> {
+    let args = s.strip_prefix(name)?.trim_start().strip_prefix('(')?;
+    let args = args.strip_suffix(')')?;
+    Some(args.split(',').map(str::trim).collect())
+}
+
+fn parse_rgb(args: &[&str]) -> Option<Color> {
+    let [r, g, b] = args else {
+        return None;
+    };
+    Some(Color::Rgb(
+        r.parse().ok()?,
+        g.parse().ok()?,
+        b.parse().ok()?,
+    ))
+}
+
+fn parse_hsl(args: &[&str]) -> Option<Color> {
+    let [h, s, l] = args else {
+        return None;
+    };
+    let h: f64 = h.trim_end_matches("deg").parse().ok()?;
+    let percent = |value: &str| {
+        let value: f64 = value.trim_end_matches('%').parse().ok()?;
+        (0.0..=100.0).contains(&value).then_some(value / 100.0)
+    };
+    let (s, l) = (percent(s)?, percent(l)?);
+
+    // https://www.w3.org/TR/css-color-3/#hsl-color
+    let h = h.rem_euclid(360.0) / 360.0;
+    let m2 = if l <= 0.5 {
+        l * (s + 1.0)
+    } else {
+        l + s - l * s
+    };
+    let m1 = l * 2.0 - m2;
+    let hue = |h: f64| {
+        let h = h.rem_euclid(1.0);
+        let value = if h * 6.0 < 1.0 {
+            m1 + (m2 - m1) * h * 6.0
+        } else if h * 2.0 < 1.0 {
+            m2
+        } else if h * 3.0 < 2.0 {
+            m1 + (m2 - m1) * (2.0 / 3.0 - h) * 6.0
+        } else {
+            m1
+        };
+        (value * 255.0).round() as u8
+    };
+    Some(Color::Rgb(hue(h + 1.0 / 3.0), hue(h), hue(h - 1.0 / 3.0)))
+}
+
+/// Returns the known color name closest to `name`, if any is close enough to
+/// be a likely typo.
+fn suggest(name: &str) -> Option<&'static str> {
+    let names = ANSI_NAMES
+        .iter()
+        .map(|(name, _)| *name)
+        .chain(CSS_NAMES.iter().map(|(name, _)| *name));
+    let (distance, closest) = names
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .min_by_key(|(distance, _)| *distance)?;
+    (distance <= (name.len() / 3).max(2)).then_some(closest)
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = previous[j] + usize::from(ca != *cb);
+            current.push(substitute.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// How many colors the terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+    /// The 16 ANSI colors
+    Ansi16,
+    /// The xterm 256-color palette
+    Ansi256,
+    /// 24-bit RGB
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Guesses the terminal's color depth from `COLORTERM` and `TERM`.
+    ///
+    /// Terminals with 24-bit color announce it in `COLORTERM`; those that
+    /// don't are assumed to have 256 colors if `TERM` says so, and 16 otherwise.
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        if matches!(colorterm.as_str(), "truecolor" | "24bit") {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    /// Returns the closest color this depth can show.
+    pub fn downsample(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorDepth::TrueColor, _) => color,
+            (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(nearest_256([r, g, b])),
+            (ColorDepth::Ansi16, Color::Rgb(..) | Color::Indexed(_)) => {
+                rgb(color).map_or(color, nearest_16)
+            }
+            _ => color,
+        }
+    }
+
+    /// Downsamples every cell of a buffer, e.g. a frame about to be drawn.
+    pub fn downsample_buffer(self, buf: &mut Buffer) {
+        if self == ColorDepth::TrueColor {
+            return;
+        }
+        for cell in &mut buf.content {
+            cell.fg = self.downsample(cell.fg);
+            cell.bg = self.downsample(cell.bg);
+        }
+    }
+}
+
+/// Levels of each component in the 6×6×6 color cube of the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Typical RGB values of the 16 ANSI colors (xterm's defaults).
+const ANSI_RGB: [(Color, [u8; 3]); 16] = [
+    (Color::Black, [0, 0, 0]),
+    (Color::Red, [205, 0, 0]),
+    (Color::Green, [0, 205, 0]),
+    (Color::Yellow, [205, 205, 0]),
+    (Color::Blue, [0, 0, 238]),
+    (Color::Magenta, [205, 0, 205]),
+    (Color::Cyan, [0, 205, 205]),
+    (Color::Gray, [229, 229, 229]),
+    (Color::DarkGray, [127, 127, 127]),
+    (Color::LightRed, [255, 0, 0]),
+    (Color::LightGreen, [0, 255, 0]),
+    (Color::LightYellow, [255, 255, 0]),
+    (Color::LightBlue, [92, 92, 255]),
+    (Color::LightMagenta, [255, 0, 255]),
+    (Color::LightCyan, [0, 255, 255]),
+    (Color::White, [255, 255, 255]),
+];
+
+/// Resolves a terminal color to RGB using the xterm default palette, or
+/// returns `None` for `Color::Reset`, which is up to the terminal.
+pub fn rgb(color: Color) -> Option<[u8; 3]> {
+    match color {
+        Color::Reset => None,
+        Color::Rgb(r, g, b) => Some([r, g, b]),
+        Color::Indexed(index) => Some(indexed_rgb(index)),
+        _ => ANSI_RGB
+            .iter()
+            .find(|(ansi, _)| *ansi == color)
+            .map(|(_, rgb)| *rgb),
+    }
+}
+
+/// Returns the RGB value of a 256-color palette index.
+fn indexed_rgb(index: u8) -> [u8; 3] {
+    match index {
+        0..16 => ANSI_RGB[index as usize].1,
+        16..232 => {
+            let i = index - 16;
+            [i / 36, i / 6 % 6, i % 6].map(|level| CUBE_LEVELS[level as usize])
+        }
+        232.. => [8 + (index - 232) * 10; 3],
+    }
+}
+
+fn distance(a: [u8; 3], b: [u8; 3]) -> i32 {
+    (0..3).map(|i| (a[i] as i32 - b[i] as i32).pow(2)).sum()
+}
+
+/// Returns the 256-color palette index closest to an RGB color, from the
+/// color cube or the gray ramp.
+fn nearest_256(color: [u8; 3]) -> u8 {
+    (16..=255)
+        .min_by_key(|&index| distance(color, indexed_rgb(index)))
+        .unwrap_or(16)
+}
+
+fn nearest_16(color: [u8; 3]) -> Color {
+    ANSI_RGB
+        .iter()
+        .min_by_key(|(_, rgb)| distance(color, *rgb))
+        .map_or(Color::White, |(ansi, _)| *ansi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_syntax() {
+        assert_eq!(parse_color("green"), Ok(Color::Green));
+        assert_eq!(parse_color("Light Blue"), Ok(Color::LightBlue));
+        assert_eq!(
+            parse_color("rebecca-purple"),
+            Ok(Color::Rgb(0x66, 0x33, 0x99))
+        );
+        assert_eq!(parse_color("#FF8800"), Ok(Color::Rgb(255, 136, 0)));
+        assert_eq!(parse_color("#f80"), Ok(Color::Rgb(255, 136, 0)));
+        assert_eq!(parse_color("rgb(255, 136, 0)"), Ok(Color::Rgb(255, 136, 0)));
+        assert_eq!(
+            parse_color("hsl(32, 100%, 50%)"),
+            Ok(Color::Rgb(255, 136, 0))
+        );
+        assert_eq!(
+            parse_color("hsl(0, 0%, 100%)"),
+            Ok(Color::Rgb(255, 255, 255))
+        );
+        assert_eq!(parse_color("208"), Ok(Color::Indexed(208)));
+    }
+
+    #[test]
+    fn typos_are_errors_with_suggestions() {
+        assert_eq!(
+            parse_color("gren"),
+            Err("unknown color 'gren' (did you mean 'green'?)".to_string())
+        );
+        assert!(
+            parse_color("lightsteelblu")
+                .unwrap_err()
+                .contains("'lightsteelblue'")
+        );
+        assert!(
+            parse_color("zzzzzzzz")
+                .unwrap_err()
+                .contains("expected a color name")
+        );
+        assert!(
+            parse_color("#12345")
+                .unwrap_err()
+                .contains("#RGB or #RRGGBB")
+        );
+        assert!(parse_color("rgb(256, 0, 0)").is_err());
+        assert!(parse_color("hsl(0, 120%, 50%)").is_err());
+        assert!(parse_color("256").unwrap_err().contains("out of range"));
+    }
+
+    #[test]
+    fn downsampling_picks_the_nearest_palette_color() {
+        let orange = Color::Rgb(255, 136, 0);
+        assert_eq!(ColorDepth::TrueColor.downsample(orange), orange);
+        assert_eq!(ColorDepth::Ansi256.downsample(orange), Color::Indexed(208));
+        assert_eq!(
+            ColorDepth::Ansi256.downsample(Color::Rgb(8, 8, 8)),
+            Color::Indexed(232)
+        );
+        assert_eq!(ColorDepth::Ansi16.downsample(orange), Color::Yellow);
+        assert_eq!(
+            ColorDepth::Ansi16.downsample(Color::Indexed(196)),
+            Color::LightRed
+        );
+        assert_eq!(ColorDepth::Ansi16.downsample(Color::Green), Color::Green);
+    }
+}
@@ -1,31 +1,85 @@
 //! Configuration loading and color parsing.
+//!
+//! Settings come from several layers, each overriding the ones before it:
+//! built-in defaults, the theme's colors, the config file, the selected
+//! profile, the script's front matter, its playlist entry, `TP_*` environment
+//! variables and the command line.
+//! `Layers` merges them and remembers where each value came from.
 
-use ratatui::style::Color;
+pub use crate::color::parse_color;
+pub use crate::options::{FontScale, FontSize, OtherLines};
+use crate::theme::Theme;
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::path::{Path, PathBuf};
 
 /// Root configuration structure.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     pub display: DisplayConfig,
     pub scroll: ScrollConfig,
+    pub script: ScriptConfig,
+    /// Text color per speaker name in `NAME: line` scripts
+    pub speakers: BTreeMap<String, String>,
+    /// Named sets of settings selected with `--profile`, each laid out like
+    /// the config file. Only the keys a profile sets override the rest
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Config>,
 }
 
 /// Display-related settings.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct DisplayConfig {
-    /// Font scale: 1=small (4x4), 2=medium (8x4), 3=large (8x8)
-    pub font_scale: u8,
-    /// Text color name or hex (e.g., "white", "#FF0000")
-    pub text_color: String,
-    /// Background color name or hex
-    pub background_color: String,
+    /// Font size by name, 1=quadrant (4x4), 2=half-height (8x4), 3=full (8x8),
+    /// or "auto"
+    pub font_scale: FontScale,
+    /// With `font_scale = "auto"`: fewest characters a line must fit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_line_chars: Option<usize>,
+    /// With `font_scale = "auto"`: fewest words of the script's average length
+    /// a line must fit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_line_words: Option<usize>,
+    /// Color theme: a built-in name, the name of a file in the themes
+    /// directory, or a path. Applied by `Config::load_theme`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    /// Text color name or hex (e.g., "white", "#FF0000"); white if neither
+    /// this nor the theme sets it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_color: Option<String>,
+    /// Background color name or hex; black if neither this nor the theme sets it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+    /// Color of section marker labels; the text color if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highlight_color: Option<String>,
+    /// Color of the rules either side of a section marker; the text color if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guide_color: Option<String>,
+    /// Color of the status bar; dimmed text color if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_color: Option<String>,
+    /// Color of dimmed context lines; dimmed text color if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimmed_color: Option<String>,
     /// Horizontal padding as percentage of screen width (0-40)
     pub horizontal_padding: u16,
+    /// How lines are aligned between the paddings
+    pub align: Align,
+    /// Blank terminal rows between lines
+    pub line_spacing: u16,
+    /// Extra blank terminal rows at paragraph breaks (empty source lines)
+    pub paragraph_spacing: u16,
 }
 
 /// Scroll behavior settings.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ScrollConfig {
     /// Lines to scroll per second
     pub speed: f64,
@@ -33,13 +87,47 @@ pub struct ScrollConfig {
     pub start_position: f64,
 }
 
+/// Script prompting settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScriptConfig {
+    /// How lines that don't belong to the selected character or speaker are shown
+    pub others: OtherLines,
+    /// Speaker to prompt in a multi-speaker script
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+}
+
+/// Horizontal alignment of the big text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    Left,
+    #[default]
+    Center,
+    Right,
+    /// Stretch the spaces between words so lines fill the width; the last line
+    /// of a paragraph is left-aligned
+    Justify,
+}
+
 impl Default for DisplayConfig {
     fn default() -> Self {
         Self {
-            font_scale: 2,
-            text_color: "white".to_string(),
-            background_color: "black".to_string(),
+            font_scale: FontScale::default(),
+            min_line_chars: None,
+            min_line_words: None,
+            theme: None,
+            text_color: None,
+            background_color: None,
+            highlight_color: None,
+            guide_color: None,
+            status_color: None,
+            dimmed_color: None,
             horizontal_padding: 10,
+            align: Align::default(),
+            line_spacing: 0,
+            paragraph_spacing: 0,
         }
     }
 }
@@ -54,15 +142,80 @@ impl Default for ScrollConfig {
 }
 
 impl Config {
-    /// Loads configuration from the default path, or returns defaults if not found.
-    pub fn load() -> anyhow::Result<Self> {
-        let config_path = Self::config_path()?;
-        if config_path.exists() {
-            let content = std::fs::read_to_string(&config_path)?;
-            let config: Config = toml::from_str(&content)?;
-            Ok(config)
-        } else {
-            Ok(Config::default())
+    /// Checks the values a config file can get wrong without being invalid
+    /// TOML: colors that don't parse and numbers out of range.
+    pub fn check(&self) -> anyhow::Result<()> {
+        self.check_colors()?;
+        if self.display.horizontal_padding > 40 {
+            anyhow::bail!(
+                "display.horizontal_padding: {} is out of range (0-40)",
+                self.display.horizontal_padding
+            );
+        }
+        if self.scroll.speed.is_nan() || self.scroll.speed <= 0.0 {
+            anyhow::bail!(
+                "scroll.speed: {} is not a positive number of lines per second",
+                self.scroll.speed
+            );
+        }
+        Ok(())
+    }
+
+    /// Loads the theme named by `display.theme`, if any, and applies it.
+    pub fn load_theme(&mut self) -> anyhow::Result<()> {
+        if let Some(name) = &self.display.theme {
+            let theme = Theme::load(name)?;
+            self.apply_theme(theme);
+        }
+        Ok(())
+    }
+
+    /// Checks that every color in the config parses, naming the first one
+    /// that doesn't.
+    pub fn check_colors(&self) -> anyhow::Result<()> {
+        let display = &self.display;
+        let keys = [
+            ("display.text_color", &display.text_color),
+            ("display.background_color", &display.background_color),
+            ("display.highlight_color", &display.highlight_color),
+            ("display.guide_color", &display.guide_color),
+            ("display.status_color", &display.status_color),
+            ("display.dimmed_color", &display.dimmed_color),
+        ];
+        for (key, color) in keys {
+            if let Some(color) = color {
+                parse_color(color).map_err(|e| anyhow::anyhow!("{}: {}", key, e))?;
+            }
+        }
+        for (name, color) in &self.speakers {
+            parse_color(color).map_err(|e| anyhow::anyhow!("speakers.{}: {}", name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Fills in every color the config leaves unset from `theme`.
+    pub fn apply_theme(&mut self, theme: Theme) {
+        let display = &mut self.display;
+        for (color, themed) in [
+            (&mut display.text_color, theme.text),
+            (&mut display.background_color, theme.background),
+            (&mut display.highlight_color, theme.highlight),
+            (&mut display.guide_color, theme.guide),
+            (&mut display.status_color, theme.status_bar),
+            (&mut display.dimmed_color, theme.dimmed),
+        ] {
+            if color.is_none() {
+                *color = themed;
+            }
+        }
+        for (name, color) in theme.speakers {
+            if !self
+                .speakers
+                .keys()
+                .any(|key| key.eq_ignore_ascii_case(&name))
+            {
+                self.speakers.insert(name, color);
+            }
         }
     }
 
@@ -72,35 +225,652 @@ impl Config {
             .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
         Ok(config_dir.join("tui_prompter").join("config.toml"))
     }
+
+    /// Returns the display colors worth saving: those set in the config file,
+    /// a profile or on the command line. Colors from a theme, the script or
+    /// the environment only apply to the run that has them.
+    pub fn chosen_colors(&self, sources: &Sources) -> toml::Table {
+        let colors = &self.display;
+        let mut chosen = toml::Table::new();
+        for (key, color) in [
+            ("text_color", &colors.text_color),
+            ("background_color", &colors.background_color),
+            ("highlight_color", &colors.highlight_color),
+            ("guide_color", &colors.guide_color),
+            ("status_color", &colors.status_color),
+            ("dimmed_color", &colors.dimmed_color),
+        ] {
+            let saved = matches!(
+                sources.get(&format!("display.{}", key)),
+                Some(Source::File(_) | Source::Profile(_) | Source::CommandLine)
+            );
+            if let Some(color) = color.as_ref().filter(|_| saved) {
+                chosen.insert(key.into(), color.clone().into());
+            }
+        }
+        chosen
+    }
+
+    /// Formats the config as TOML with a comment after each value naming its
+    /// source. Values missing from `sources` are defaults.
+    pub fn annotated(&self, sources: &Sources) -> String {
+        let table = toml::Table::try_from(self).expect("config serializes to TOML");
+        let mut out = String::new();
+        for (section, values) in &table {
+            if section == "profiles" {
+                continue;
+            }
+            let Some(values) = values.as_table().filter(|values| !values.is_empty()) else {
+                continue;
+            };
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            let _ = writeln!(out, "[{}]", section);
+            let lines: Vec<_> = values
+                .iter()
+                .map(|(key, value)| {
+                    let source = sources
+                        .get(&format!("{}.{}", section, key))
+                        .unwrap_or(&Source::Default);
+                    (format!("{} = {}", toml_key(key), value), source)
+                })
+                .collect();
+            let width = lines.iter().map(|(line, _)| line.len()).max().unwrap_or(0);
+            for (line, source) in lines {
+                let _ = writeln!(out, "{:width$}  # {}", line, source);
+            }
+        }
+        out
+    }
+}
+
+/// The sections `TP_<SECTION>__<KEY>` variables can set.
+const ENV_SECTIONS: [&str; 4] = ["display", "scroll", "script", "speakers"];
+
+/// The config file `tp config init` writes: every default, with comments.
+pub const DEFAULT_CONFIG: &str = r##"# tp configuration. Settings given on the command line override these, and
+# every key is optional: delete one to get its default back.
+
+[display]
+# Color theme: classic, high-contrast, amber, solarized, the name of a file in
+# the themes directory next to this file, or a path to a theme file
+# theme = "classic"
+
+# Font size: sextant, quadrant, third-height, half-height, half-width, full,
+# double, triple, or "auto" for the largest size that fits min_line_chars
+# characters or min_line_words words on a line
+font_scale = "half-height"
+# min_line_chars = 12
+# min_line_words = 4
+
+# Colors: names (white, orange, dark-gray), hex (#FFB000), rgb(), hsl() or a
+# 256-color index. Unset colors come from the theme
+# text_color = "white"
+# background_color = "black"
+# highlight_color = "yellow"    # section marker labels
+# guide_color = "gray"          # rules either side of section markers
+# status_color = "gray"         # status bar
+# dimmed_color = "darkgray"     # other characters' or speakers' lines
+
+# Percentage of the screen width left blank on each side (0-40)
+horizontal_padding = 10
+# "left", "center", "right" or "justify"
+align = "center"
+# Blank rows between lines, and extra blank rows at empty lines in the script
+line_spacing = 0
+paragraph_spacing = 0
+
+[scroll]
+# Lines per second
+speed = 2.0
+
+[script]
+# Other characters' or speakers' lines: "dim", "smaller" or "hide"
+others = "dim"
+# Speaker to prompt in a `NAME: line` script
+# speaker = "ALICE"
+
+# Text colors for speakers in `NAME: line` scripts
+[speakers]
+# ALICE = "lightcyan"
+# BOB = "#FFB000"
+
+# Named sets of settings, picked with --profile NAME or TP_PROFILE=NAME. A
+# profile can set anything above, and only what it sets replaces the rest
+# [profiles.studio.display]
+# font_scale = "full"
+# text_color = "green"
+"##;
+
+/// Where a setting's value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    /// Filled in from the named theme
+    Theme(String),
+    File(PathBuf),
+    /// A `[profiles.<name>]` section of the config file
+    Profile(String),
+    /// The front matter at the top of the script
+    FrontMatter,
+    /// The script's entry in a playlist
+    Playlist,
+    /// The named environment variable
+    Environment(String),
+    CommandLine,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => f.write_str("default"),
+            Source::Theme(name) => write!(f, "theme {}", name),
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Profile(name) => write!(f, "profile {}", name),
+            Source::FrontMatter => f.write_str("front matter"),
+            Source::Playlist => f.write_str("playlist"),
+            Source::Environment(name) => write!(f, "environment {}", name),
+            Source::CommandLine => f.write_str("command line"),
+        }
+    }
 }
 
-/// Parses a color string into a ratatui Color.
-///
-/// Supports named colors (e.g., "red", "lightblue") and hex codes (e.g., "#FF0000").
-pub fn parse_color(color_str: &str) -> Color {
-    match color_str.to_lowercase().as_str() {
-        "black" => Color::Black,
-        "red" => Color::Red,
-        "green" => Color::Green,
-        "yellow" => Color::Yellow,
-        "blue" => Color::Blue,
-        "magenta" => Color::Magenta,
-        "cyan" => Color::Cyan,
-        "gray" | "grey" => Color::Gray,
-        "darkgray" | "darkgrey" => Color::DarkGray,
-        "lightred" => Color::LightRed,
-        "lightgreen" => Color::LightGreen,
-        "lightyellow" => Color::LightYellow,
-        "lightblue" => Color::LightBlue,
-        "lightmagenta" => Color::LightMagenta,
-        "lightcyan" => Color::LightCyan,
-        "white" => Color::White,
-        s if s.starts_with('#') && s.len() == 7 => {
-            let r = u8::from_str_radix(&s[1..3], 16).unwrap_or(255);
-            let g = u8::from_str_radix(&s[3..5], 16).unwrap_or(255);
-            let b = u8::from_str_radix(&s[5..7], 16).unwrap_or(255);
-            Color::Rgb(r, g, b)
-        }
-        _ => Color::White,
+/// The source of each setting, keyed by dotted path (`display.text_color`).
+pub type Sources = BTreeMap<String, Source>;
+
+/// Partial configs from several sources, each overriding the ones before it.
+#[derive(Debug, Clone, Default)]
+pub struct Layers {
+    layers: Vec<(Source, toml::Table)>,
+}
+
+impl Layers {
+    /// Adds settings that override those added before.
+    pub fn push(&mut self, source: Source, table: toml::Table) {
+        self.layers.push((source, table));
+    }
+
+    /// Adds the settings in a config file, checking that they make a valid
+    /// config on their own.
+    pub fn push_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        let content = read_config(path)?;
+        parse_config(path, &content)?;
+        let table = content.parse().expect("a valid config is a valid table");
+        self.push(Source::File(path.to_path_buf()), table);
+        Ok(())
+    }
+
+    /// Adds the profile called `name` from the config files added so far.
+    pub fn push_profile(&mut self, name: &str) -> anyhow::Result<()> {
+        let profile = self.layers.iter().rev().find_map(|(_, table)| {
+            table
+                .get("profiles")?
+                .as_table()?
+                .iter()
+                .find(|(profile, _)| profile.eq_ignore_ascii_case(name))
+                .and_then(|(profile, table)| Some((profile.clone(), table.as_table()?.clone())))
+        });
+        let Some((name, table)) = profile else {
+            let names: Vec<_> = self
+                .layers
+                .iter()
+                .filter_map(|(_, table)| table.get("profiles")?.as_table())
+                .flat_map(|profiles| profiles.keys().cloned())
+                .collect();
+            if names.is_empty() {
+                anyhow::bail!(
+                    "Unknown profile '{}': the config file has no [profiles.<name>] sections",
+                    name
+                );
+            }
+            anyhow::bail!(
+                "Unknown profile '{}' (profiles: {})",
+                name,
+                names.join(", ")
+            );
+        };
+        self.push(Source::Profile(name), table);
+        Ok(())
+    }
+
+    /// Adds a setting for each `TP_<SECTION>__<KEY>` variable, such as
+    /// `TP_DISPLAY__TEXT_COLOR` or `TP_SPEAKERS__ALICE`, where the section is
+    /// one of the config file's. Other variables are ignored.
+    ///
+    /// Values are read as TOML (`3`, `2.5`, `true`) where the key takes a
+    /// number or flag, and as text otherwise. A variable that isn't a valid
+    /// setting is skipped, and returned as a warning.
+    pub fn push_env(&mut self, vars: impl IntoIterator<Item = (String, String)>) -> Vec<String> {
+        let mut vars: Vec<_> = vars
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let (section, key) = name.strip_prefix("TP_")?.split_once("__")?;
+                let section = section.to_ascii_lowercase();
+                if !ENV_SECTIONS.contains(&section.as_str()) {
+                    return None;
+                }
+                // Speaker names are looked up ignoring case, but keep them as given
+                let key = if section == "speakers" {
+                    key.to_string()
+                } else {
+                    key.to_ascii_lowercase()
+                };
+                Some((name, section, key, value))
+            })
+            .collect();
+        vars.sort();
+
+        let mut warnings = Vec::new();
+        for (name, section, key, value) in vars {
+            let setting = |value: toml::Value| {
+                let mut table = toml::Table::new();
+                table.insert(key.clone(), value);
+                let mut layer = toml::Table::new();
+                layer.insert(section.clone(), table.into());
+                layer
+            };
+            let parsed = format!("value = {}", value)
+                .parse::<toml::Table>()
+                .ok()
+                .and_then(|mut table| table.remove("value"))
+                .filter(|value| !value.is_str());
+            let valid =
+                |layer: &toml::Table| toml::Value::Table(layer.clone()).try_into::<Config>();
+            let layer = match parsed.map(setting).filter(|layer| valid(layer).is_ok()) {
+                Some(layer) => layer,
+                None => {
+                    let layer = setting(value.into());
+                    if let Err(error) = valid(&layer) {
+                        warnings.push(format!(
+                            "Ignoring environment variable {}: {}",
+                            name,
+                            error.to_string().trim_end().replace('\n', " ")
+                        ));
+                        continue;
+                    }
+                    layer
+                }
+            };
+            self.push(Source::Environment(name), layer);
+        }
+        warnings
+    }
+
+    /// Merges the layers, applies the resulting theme and checks the values.
+    /// Returns the config along with the source of each setting that isn't a
+    /// default.
+    pub fn resolve(&self) -> anyhow::Result<(Config, Sources)> {
+        let mut merged = toml::Table::new();
+        let mut sources = Sources::new();
+        for (source, table) in &self.layers {
+            merge(&mut merged, table, "", source, &mut sources);
+        }
+        let mut config: Config = toml::Value::Table(merged).try_into()?;
+
+        if let Some(name) = config.display.theme.clone() {
+            let before = flatten(&config);
+            config.load_theme()?;
+            for (key, value) in flatten(&config) {
+                if before.get(&key) != Some(&value) {
+                    sources.insert(key, Source::Theme(name.clone()));
+                }
+            }
+        }
+
+        config.check()?;
+        Ok((config, sources))
+    }
+}
+
+/// Writes `settings`, laid out like the config file, into the config file at
+/// `path`, or into its `[profiles.<profile>]` sections. The file is created if
+/// it doesn't exist; otherwise only the values saved change, and comments and
+/// formatting are kept.
+pub fn save_settings(
+    path: &Path,
+    profile: Option<&str>,
+    settings: &toml::Table,
+) -> anyhow::Result<()> {
+    let content = if path.exists() {
+        read_config(path)?
+    } else {
+        String::new()
+    };
+    let mut document: toml_edit::DocumentMut = content
+        .parse()
+        .with_context(|| format!("Invalid config file {}", path.display()))?;
+
+    let mut root: &mut dyn toml_edit::TableLike = document.as_table_mut();
+    if let Some(profile) = profile {
+        let profiles = child_table(root, "profiles")?;
+        // Save into an existing profile however its name is capitalized
+        let name = profiles
+            .iter()
+            .map(|(name, _)| name)
+            .find(|name| name.eq_ignore_ascii_case(profile))
+            .unwrap_or(profile)
+            .to_string();
+        root = child_table(profiles, &name)?;
+    }
+    for (section, values) in settings {
+        let Some(values) = values.as_table() else {
+            continue;
+        };
+        let table = child_table(root, section)?;
+        for (key, value) in values {
+            let mut value = edit_value(value);
+            // Keep a comment after the old value
+            if let Some(old) = table.get(key).and_then(|item| item.as_value()) {
+                *value.decor_mut() = old.decor().clone();
+            }
+            table.insert(key, toml_edit::Item::Value(value));
+        }
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    std::fs::write(path, document.to_string())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Returns the table at `key` in `table`, adding it if it's missing.
+fn child_table<'a>(
+    table: &'a mut dyn toml_edit::TableLike,
+    key: &str,
+) -> anyhow::Result<&'a mut dyn toml_edit::TableLike> {
+    if !table.contains_key(key) {
+        let mut child = toml_edit::Table::new();
+        child.set_implicit(true);
+        table.insert(key, toml_edit::Item::Table(child));
+    }
+    table
+        .get_mut(key)
+        .and_then(|item| item.as_table_like_mut())
+        .ok_or_else(|| anyhow::anyhow!("{} in the config file is not a table", key))
+}
+
+/// Converts a value for `save_settings`.
+fn edit_value(value: &toml::Value) -> toml_edit::Value {
+    match value {
+        toml::Value::String(s) => s.as_str().into(),
+        toml::Value::Integer(i) => (*i).into(),
+        toml::Value::Float(f) => (*f).into(),
+        toml::Value::Boolean(b) => (*b).into(),
+        // Settings are never dates, arrays or tables; write them as TOML text
+        other => other.to_string().as_str().into(),
+    }
+}
+
+/// Reads a config file.
+fn read_config(path: &Path) -> anyhow::Result<String> {
+    std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// Parses a config file, rejecting unknown keys and nested profiles.
+fn parse_config(path: &Path, content: &str) -> anyhow::Result<Config> {
+    let config: Config = toml::from_str(content)
+        .with_context(|| format!("Invalid config file {}", path.display()))?;
+    if let Some((name, _)) = config
+        .profiles
+        .iter()
+        .find(|(_, profile)| !profile.profiles.is_empty())
+    {
+        anyhow::bail!(
+            "Invalid config file {}: profiles.{} can't contain profiles",
+            path.display(),
+            name
+        );
+    }
+    Ok(config)
+}
+
+/// Copies `layer` over `merged`, merging tables key by key and recording
+/// `source` for each value copied.
+fn merge(
+    merged: &mut toml::Table,
+    layer: &toml::Table,
+    prefix: &str,
+    source: &Source,
+    sources: &mut Sources,
+) {
+    for (key, value) in layer {
+        let path = format!("{}{}", prefix, key);
+        match (merged.get_mut(key), value) {
+            (Some(toml::Value::Table(merged)), toml::Value::Table(table)) => {
+                merge(merged, table, &format!("{}.", path), source, sources);
+            }
+            _ => {
+                merged.insert(key.clone(), value.clone());
+                if let toml::Value::Table(table) = value {
+                    // Record the values inside a section added whole
+                    merge(
+                        &mut toml::Table::new(),
+                        table,
+                        &format!("{}.", path),
+                        source,
+                        sources,
+                    );
+                } else {
+                    sources.insert(path, source.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Returns every value in the config, apart from profiles, by dotted path.
+fn flatten(config: &Config) -> BTreeMap<String, toml::Value> {
+    let mut table = toml::Table::try_from(config).expect("config serializes to TOML");
+    table.remove("profiles");
+    let mut values = BTreeMap::new();
+    for (section, table) in table {
+        if let toml::Value::Table(table) = table {
+            for (key, value) in table {
+                values.insert(format!("{}.{}", section, key), value);
+            }
+        }
+    }
+    values
+}
+
+/// Quotes a key unless it can be written bare.
+fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        key.to_string()
+    } else {
+        toml::Value::String(key.to_string()).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(source: &str) -> toml::Table {
+        source.parse().unwrap()
+    }
+
+    #[test]
+    fn default_config_file_holds_the_defaults() {
+        let config: Config = toml::from_str(DEFAULT_CONFIG).unwrap();
+        assert_eq!(flatten(&config), flatten(&Config::default()));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected_with_their_position() {
+        let error = toml::from_str::<Config>("[display]\ntext_colour = \"red\"\n").unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("line 2, column 1"), "{message}");
+        assert!(message.contains("unknown field `text_colour`"), "{message}");
+    }
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let file = Source::File(PathBuf::from("config.toml"));
+        let mut layers = Layers::default();
+        layers.push(
+            file.clone(),
+            table("[display]\ntheme = \"amber\"\ntext_color = \"red\"\nhorizontal_padding = 5\n"),
+        );
+        layers.push(
+            Source::CommandLine,
+            table("[display]\nhorizontal_padding = 20\n"),
+        );
+        let (config, sources) = layers.resolve().unwrap();
+
+        assert_eq!(config.display.horizontal_padding, 20);
+        assert_eq!(config.display.text_color.as_deref(), Some("red"));
+        assert_eq!(sources["display.horizontal_padding"], Source::CommandLine);
+        assert_eq!(sources["display.text_color"], file);
+        assert_eq!(
+            sources["display.background_color"],
+            Source::Theme("amber".to_string())
+        );
+        assert!(!sources.contains_key("scroll.speed"));
+
+        layers.push(
+            Source::CommandLine,
+            table("[display]\nhorizontal_padding = 50\n"),
+        );
+        let error = layers.resolve().unwrap_err().to_string();
+        assert!(error.contains("out of range (0-40)"), "{error}");
+    }
+
+    #[test]
+    fn profiles_override_the_file_but_not_the_command_line() {
+        let mut layers = Layers::default();
+        layers.push(
+            Source::File(PathBuf::from("config.toml")),
+            table(
+                "[scroll]\nspeed = 3.0\n\n[profiles.studio.display]\nfont_scale = 3\nhorizontal_padding = 0\n\n[profiles.studio.scroll]\nspeed = 1.5\n",
+            ),
+        );
+        layers.push_profile("Studio").unwrap();
+        layers.push(
+            Source::CommandLine,
+            table("[display]\nhorizontal_padding = 5\n"),
+        );
+        let (config, sources) = layers.resolve().unwrap();
+
+        assert_eq!(config.display.font_scale, FontScale::Fixed(FontSize::Full));
+        assert_eq!(config.scroll.speed, 1.5);
+        assert_eq!(config.display.horizontal_padding, 5);
+        let studio = Source::Profile("studio".to_string());
+        assert_eq!(sources["display.font_scale"], studio);
+        assert_eq!(sources["scroll.speed"], studio);
+
+        let error = layers.push_profile("podcast").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unknown profile 'podcast' (profiles: studio)"
+        );
+    }
+
+    #[test]
+    fn environment_variables_set_any_key() {
+        let vars = [
+            ("TP_DISPLAY__TEXT_COLOR", "208"),
+            ("TP_DISPLAY__FONT_SCALE", "full"),
+            ("TP_SCROLL__SPEED", "3"),
+            ("TP_SPEAKERS__ALICE", "red"),
+            ("TP_PROFILE", "studio"),
+            ("HOME", "/root"),
+        ];
+        let mut layers = Layers::default();
+        let warnings =
+            layers.push_env(vars.map(|(name, value)| (name.to_string(), value.to_string())));
+        assert_eq!(warnings, Vec::<String>::new());
+        let (config, sources) = layers.resolve().unwrap();
+
+        assert_eq!(config.display.text_color.as_deref(), Some("208"));
+        assert_eq!(config.display.font_scale, FontScale::Fixed(FontSize::Full));
+        assert_eq!(config.scroll.speed, 3.0);
+        assert_eq!(config.speakers["ALICE"], "red");
+        assert_eq!(
+            sources["scroll.speed"],
+            Source::Environment("TP_SCROLL__SPEED".to_string())
+        );
+
+        let mut layers = Layers::default();
+        let warnings = layers.push_env([
+            ("TP_DISPLAY__TEXT_COLOUR".to_string(), "red".to_string()),
+            ("TP_BUILD__CACHE".to_string(), "/tmp".to_string()),
+            ("TP_SCROLL__SPEED".to_string(), "2".to_string()),
+        ]);
+        let [warning] = &warnings[..] else {
+            panic!("expected one warning: {warnings:?}");
+        };
+        assert!(
+            warning.starts_with("Ignoring environment variable TP_DISPLAY__TEXT_COLOUR: "),
+            "{warning}"
+        );
+        assert!(warning.contains("unknown field `text_colour`"), "{warning}");
+        let (config, _) = layers.resolve().unwrap();
+        assert_eq!(config.scroll.speed, 2.0);
+    }
+
+    #[test]
+    fn saving_keeps_comments_and_formatting() {
+        let path = std::env::temp_dir().join(format!("tp-save-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "# My settings\n[display]\nfont_scale = \"full\"  # big\ntext_color = \"green\"\n\n[profiles.Studio.scroll]\nspeed = 1.0\n",
+        )
+        .unwrap();
+        let settings = table(
+            "[display]\nfont_scale = \"double\"\nhorizontal_padding = 20\n\n[scroll]\nspeed = 2.5\n",
+        );
+
+        save_settings(&path, None, &settings).unwrap();
+        save_settings(&path, Some("studio"), &table("[scroll]\nspeed = 3.0\n")).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            saved,
+            "# My settings\n[display]\nfont_scale = \"double\"  # big\ntext_color = \"green\"\nhorizontal_padding = 20\n\n[profiles.Studio.scroll]\nspeed = 3.0\n\n[scroll]\nspeed = 2.5\n"
+        );
+    }
+
+    #[test]
+    fn only_colors_chosen_for_every_run_are_saved() {
+        let mut layers = Layers::default();
+        layers.push(
+            Source::File(PathBuf::from("config.toml")),
+            table("[display]\ntext_color = \"green\"\ntheme = \"amber\"\n"),
+        );
+        layers.push(
+            Source::FrontMatter,
+            table("[display]\nbackground_color = \"navy\"\n"),
+        );
+        layers.push(
+            Source::Environment("TP_DISPLAY__GUIDE_COLOR".to_string()),
+            table("[display]\nguide_color = \"red\"\n"),
+        );
+        layers.push(
+            Source::CommandLine,
+            table("[display]\nstatus_color = \"white\"\n"),
+        );
+        let (config, sources) = layers.resolve().unwrap();
+
+        assert_eq!(
+            config.chosen_colors(&sources).to_string(),
+            "text_color = \"green\"\nstatus_color = \"white\"\n"
+        );
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        let mut layers = Layers::default();
+        layers.push(Source::CommandLine, table("[scroll]\nspeed = 0.0\n"));
+        let error = layers.resolve().unwrap_err();
+        assert!(error.to_string().starts_with("scroll.speed:"), "{error}");
     }
 }
@@ -5,30 +5,64 @@ use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use std::time::Duration;
 
-/// Polls for keyboard events and dispatches them to the app.
+/// Every key, as `?` shows them over the text.
+pub const KEYS: &str = "\
+Space/p  pause or resume       r/Home  back to the start
++/-      speed up, slow down   End     go to the end
+↑/↓ j/k  scroll a line         PgUp/PgDn  scroll 10 lines
+f/F      larger/smaller font   m       cycle padding
+s        save to the config    ]/[     next/previous segment
+Ctrl-Z   suspend               q/Esc   quit
+?        show or hide this list";
+
+/// How long the key list stays up unless `?` is pressed again.
+const KEYS_OVERLAY: Duration = Duration::from_secs(15);
+
+/// Waits up to `timeout` for an input event and dispatches it to the app.
 ///
-/// Uses a 16ms timeout for ~60fps animation smoothness.
-pub fn handle_events(app: &mut App) -> Result<()> {
-    if event::poll(Duration::from_millis(16))?
-        && let Event::Key(key) = event::read()?
-    {
-        handle_key_event(app, key);
+/// Returns whether the screen needs redrawing, i.e. a key was handled or the
+/// terminal was resized.
+pub fn handle_events(app: &mut App, timeout: Duration) -> Result<bool> {
+    if !event::poll(timeout)? {
+        return Ok(false);
+    }
+    match event::read()? {
+        Event::Key(key) => {
+            handle_key_event(app, key);
+            Ok(true)
+        }
+        Event::Resize(..) => Ok(true),
+        _ => Ok(false),
     }
-    Ok(())
 }
 
-fn handle_key_event(app: &mut App, key: KeyEvent) {
+/// Applies a single key press to the app.
+pub fn handle_key_event(app: &mut App, key: KeyEvent) {
+    app.notice = None;
     match key.code {
         KeyCode::Char('q') | KeyCode::Esc => app.should_quit = true,
         KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
             app.should_quit = true
         }
+        KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.should_suspend = true
+        }
         KeyCode::Char(' ') | KeyCode::Char('p') => app.toggle_pause(),
         KeyCode::Char('+') | KeyCode::Char('=') => app.speed_up(),
         KeyCode::Char('-') | KeyCode::Char('_') => app.speed_down(),
         KeyCode::Up | KeyCode::Char('k') => app.scroll_up(),
         KeyCode::Down | KeyCode::Char('j') => app.scroll_down(),
         KeyCode::Char('r') | KeyCode::Home => app.reset(),
+        KeyCode::Char('f') => app.cycle_font_scale(),
+        KeyCode::Char('F') => app.cycle_font_scale_back(),
+        KeyCode::Char('m') => app.cycle_padding(),
+        KeyCode::Char('s') => app.should_save = true,
+        KeyCode::Char(']') => app.next_segment(),
+        KeyCode::Char('[') => app.previous_segment(),
+        KeyCode::Char('?') => match &app.overlay {
+            Some((message, _)) if message == KEYS => app.overlay = None,
+            _ => app.show_overlay(KEYS, KEYS_OVERLAY),
+        },
         KeyCode::PageUp => {
             for _ in 0..10 {
                 app.scroll_up();
@@ -40,8 +74,35 @@ fn handle_key_event(app: &mut App, key: KeyEvent) {
             }
         }
         KeyCode::End => {
-            app.scroll_offset = app.wrapped_lines.len() as f64;
+            app.scroll_to_end();
         }
         _ => {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn question_mark_shows_and_hides_the_keys() {
+        let mut app = App::new("Hello".to_string(), &Config::default()).unwrap();
+        let question = KeyEvent::from(KeyCode::Char('?'));
+        handle_key_event(&mut app, question);
+        assert_eq!(
+            app.overlay.as_ref().map(|(message, _)| message.as_str()),
+            Some(KEYS)
+        );
+        handle_key_event(&mut app, question);
+        assert!(app.overlay.is_none());
+
+        // A config error stays readable until `?` replaces it
+        app.show_overlay("bad config", KEYS_OVERLAY);
+        handle_key_event(&mut app, question);
+        assert_eq!(
+            app.overlay.as_ref().map(|(message, _)| message.as_str()),
+            Some(KEYS)
+        );
+    }
+}
@@ -0,0 +1,467 @@
+//! Recording the scrolling prompter as an asciicast or animated GIF.
+//!
+//! Playback runs on a [`ManualClock`], so a recording takes as long as it
+//! takes to render, not as long as the script takes to scroll. Only frames that
+//! differ from the previous one are written.
+
+use crate::app::App;
+use crate::clock::ManualClock;
+use crate::color::{self, ColorDepth};
+pub use crate::options::ExportFormat;
+use crate::snapshot::{buffer_to_ansi, render_to_buffer};
+use anyhow::{Context, Result};
+use font8x8::{BASIC_FONTS, BLOCK_FONTS, BOX_FONTS, LATIN_FONTS, UnicodeFonts};
+use ratatui::{
+    buffer::Buffer,
+    style::{Color, Modifier},
+};
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::Duration;
+
+/// Recordings stop here even if the script has not finished scrolling.
+const MAX_DURATION: Duration = Duration::from_secs(4 * 60 * 60);
+
+/// Pixel size of one terminal cell in GIF output.
+const CELL_WIDTH: usize = 8;
+const CELL_HEIGHT: usize = 16;
+
+/// Colors used for `Color::Reset` in GIF output.
+const DEFAULT_FG: [u8; 3] = [229, 229, 229];
+const DEFAULT_BG: [u8; 3] = [0, 0, 0];
+
+/// Settings for [`export`].
+#[derive(Debug, Clone, Copy)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// Terminal width in columns
+    pub width: u16,
+    /// Terminal height in rows
+    pub height: u16,
+    /// Simulated frames per second, at least 1
+    pub fps: u32,
+    /// Colors an asciicast is limited to, like the terminal it was made for.
+    /// GIFs always keep every color
+    pub depth: ColorDepth,
+}
+
+/// Summary of a finished recording.
+#[derive(Debug, Clone, Copy)]
+pub struct ExportStats {
+    pub frames: usize,
+    pub duration: Duration,
+    /// True if the recording hit the length limit before the script ended
+    pub truncated: bool,
+}
+
+/// Plays `app` from its current position until it quits, or for at most four
+/// hours, and writes the recording to `writer`.
+pub fn export(app: App, options: &ExportOptions, writer: impl Write) -> Result<ExportStats> {
+    anyhow::ensure!(options.fps > 0, "fps must be at least 1");
+    let mut sink: Box<dyn FrameSink> = match options.format {
+        ExportFormat::Cast => Box::new(CastWriter::new(writer, options)?),
+        ExportFormat::Gif => Box::new(GifWriter::new(writer, options)?),
+    };
+
+    let clock = ManualClock::new();
+    let mut app = app.with_clock(clock.clone());
+    let step = Duration::from_secs_f64(1.0 / options.fps as f64);
+
+    let mut previous: Option<Buffer> = None;
+    let mut frames = 0;
+
+    loop {
+        app.update();
+        let buffer = render_to_buffer(&mut app, options.width, options.height);
+
+        if previous.as_ref() != Some(&buffer) {
+            sink.frame(clock.elapsed(), &buffer)?;
+            previous = Some(buffer);
+            frames += 1;
+        }
+
+        if app.should_quit || clock.elapsed() >= MAX_DURATION {
+            break;
+        }
+        clock.advance(step);
+    }
+
+    let duration = clock.elapsed() + step;
+    sink.finish(duration)?;
+
+    Ok(ExportStats {
+        frames,
+        duration,
+        truncated: !app.should_quit,
+    })
+}
+
+/// Receives frames in time order.
+trait FrameSink {
+    fn frame(&mut self, time: Duration, buffer: &Buffer) -> Result<()>;
+    /// Ends the recording; `end` is when the last frame stops showing.
+    fn finish(&mut self, end: Duration) -> Result<()>;
+}
+
+/// Writes asciicast v2: a JSON header line followed by one `[time, "o", data]`
+/// event per frame, each a full-screen redraw.
+struct CastWriter<W: Write> {
+    writer: W,
+    depth: ColorDepth,
+    first: bool,
+}
+
+impl<W: Write> CastWriter<W> {
+    fn new(mut writer: W, options: &ExportOptions) -> Result<Self> {
+        let header = serde_json::json!({
+            "version": 2,
+            "width": options.width,
+            "height": options.height,
+            "env": { "TERM": "xterm-256color" },
+        });
+        writeln!(writer, "{}", header)?;
+        Ok(Self {
+            writer,
+            depth: options.depth,
+            first: true,
+        })
+    }
+}
+
+impl<W: Write> FrameSink for CastWriter<W> {
+    fn frame(&mut self, time: Duration, buffer: &Buffer) -> Result<()> {
+        // Clear once and hide the cursor, then redraw from the top-left corner
+        let mut data = String::from(if self.first {
+            "\x1b[?25l\x1b[2J\x1b[H"
+        } else {
+            "\x1b[H"
+        });
+        let mut buffer = buffer.clone();
+        self.depth.downsample_buffer(&mut buffer);
+        data.push_str(buffer_to_ansi(&buffer).trim_end_matches('\n'));
+        let data = data.replace('\n', "\r\n");
+        self.first = false;
+
+        let event = serde_json::json!([time.as_secs_f64(), "o", data]);
+        writeln!(self.writer, "{}", event)?;
+        Ok(())
+    }
+
+    fn finish(&mut self, end: Duration) -> Result<()> {
+        let event = serde_json::json!([end.as_secs_f64(), "o", "\x1b[0m\x1b[?25h"]);
+        writeln!(self.writer, "{}", event)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes an animated GIF, rasterizing cells with the font8x8 bitmap font.
+///
+/// A frame's delay is only known once the next frame arrives, so one frame is
+/// always held back.
+struct GifWriter<W: Write> {
+    encoder: gif::Encoder<W>,
+    width: u16,
+    height: u16,
+    pending: Option<(Duration, gif::Frame<'static>)>,
+}
+
+impl<W: Write> GifWriter<W> {
+    fn new(writer: W, options: &ExportOptions) -> Result<Self> {
+        let width = u16::try_from(options.width as usize * CELL_WIDTH)
+            .context("Terminal too wide for a GIF")?;
+        let height = u16::try_from(options.height as usize * CELL_HEIGHT)
+            .context("Terminal too tall for a GIF")?;
+
+        let mut encoder = gif::Encoder::new(writer, width, height, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        Ok(Self {
+            encoder,
+            width,
+            height,
+            pending: None,
+        })
+    }
+
+    /// Writes the held-back frame, now that we know when it ends.
+    fn flush_pending(&mut self, until: Duration) -> Result<()> {
+        if let Some((start, mut frame)) = self.pending.take() {
+            // Round both ends so rounding errors don't accumulate over a long recording
+            let centis = |t: Duration| (t.as_millis() as f64 / 10.0).round() as u64;
+            frame.delay = (centis(until) - centis(start)).clamp(1, u16::MAX as u64) as u16;
+            self.encoder.write_frame(&frame)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> FrameSink for GifWriter<W> {
+    fn frame(&mut self, time: Duration, buffer: &Buffer) -> Result<()> {
+        self.flush_pending(time)?;
+        let (pixels, palette) = rasterize(buffer);
+        let frame = gif::Frame::from_palette_pixels(self.width, self.height, pixels, palette, None);
+        self.pending = Some((time, frame));
+        Ok(())
+    }
+
+    fn finish(&mut self, end: Duration) -> Result<()> {
+        self.flush_pending(end)?;
+        self.encoder.get_mut().flush()?;
+        Ok(())
+    }
+}
+
+/// Rasterizes a buffer into palette indices and an RGB palette of at most 256
+/// colors.
+fn rasterize(buffer: &Buffer) -> (Vec<u8>, Vec<u8>) {
+    let columns = buffer.area.width as usize;
+    let rows = buffer.area.height as usize;
+    let image_width = columns * CELL_WIDTH;
+
+    let mut palette = Palette::default();
+    let mut pixels = vec![0u8; image_width * rows * CELL_HEIGHT];
+
+    for (i, cell) in buffer.content.iter().enumerate() {
+        let (x0, y0) = ((i % columns) * CELL_WIDTH, (i / columns) * CELL_HEIGHT);
+
+        let mut fg = rgb(cell.fg, DEFAULT_FG);
+        let mut bg = rgb(cell.bg, DEFAULT_BG);
+        if cell.modifier.contains(Modifier::REVERSED) {
+            std::mem::swap(&mut fg, &mut bg);
+        }
+        if cell.modifier.contains(Modifier::DIM) {
+            fg = blend(fg, bg);
+        }
+
+        let fg = palette.index(fg);
+        let bg = palette.index(bg);
+        let glyph = cell
+            .symbol()
+            .chars()
+            .next()
+            .and_then(glyph)
+            .unwrap_or(Glyph::Bitmap([0; 8]));
+
+        for py in 0..CELL_HEIGHT {
+            let row = (y0 + py) * image_width + x0;
+            for px in 0..CELL_WIDTH {
+                pixels[row + px] = if glyph.is_set(px, py) { fg } else { bg };
+            }
+        }
+    }
+
+    (pixels, palette.into_rgb())
+}
+
+/// The shape of a cell's symbol.
+enum Glyph {
+    /// An 8x8 font8x8 bitmap
+    Bitmap([u8; 8]),
+    /// A sextant block character as six bits, left to right and top to bottom
+    Sextant(u8),
+}
+
+impl Glyph {
+    /// Returns whether pixel (`px`, `py`) of a cell is foreground.
+    fn is_set(&self, px: usize, py: usize) -> bool {
+        match self {
+            // font8x8 glyphs are 8x8, so each glyph row covers two pixel rows
+            Glyph::Bitmap(bits) => bits[py * 8 / CELL_HEIGHT] >> px & 1 == 1,
+            Glyph::Sextant(bits) => {
+                let bit = (py * 3 / CELL_HEIGHT) * 2 + px * 2 / CELL_WIDTH;
+                bits >> bit & 1 == 1
+            }
+        }
+    }
+}
+
+fn glyph(c: char) -> Option<Glyph> {
+    BASIC_FONTS
+        .get(c)
+        .or_else(|| BLOCK_FONTS.get(c))
+        .or_else(|| BOX_FONTS.get(c))
+        .or_else(|| LATIN_FONTS.get(c))
+        .map(Glyph::Bitmap)
+        .or_else(|| sextant(c).map(Glyph::Sextant))
+}
+
+/// Decodes a character from the Unicode sextant block (U+1FB00-U+1FB3B), used
+/// by the sextant and third-height font sizes.
+///
+/// The block leaves out the empty, left half, right half and full patterns,
+/// which already exist as other block elements.
+fn sextant(c: char) -> Option<u8> {
+    let index = (c as u32).checked_sub(0x1FB00).filter(|&i| i < 60)? as u8;
+    let mut bits = index + 1;
+    for skipped in [0b010101, 0b101010] {
+        if bits >= skipped {
+            bits += 1;
+        }
+    }
+    Some(bits)
+}
+
+/// Collects up to 256 distinct colors; further colors map to the nearest one.
+#[derive(Default)]
+struct Palette {
+    colors: Vec<[u8; 3]>,
+    lookup: HashMap<[u8; 3], u8>,
+}
+
+impl Palette {
+    fn index(&mut self, color: [u8; 3]) -> u8 {
+        if let Some(&index) = self.lookup.get(&color) {
+            return index;
+        }
+        if self.colors.len() < 256 {
+            let index = self.colors.len() as u8;
+            self.colors.push(color);
+            self.lookup.insert(color, index);
+            return index;
+        }
+        let distance = |c: &[u8; 3]| {
+            (0..3)
+                .map(|i| (c[i] as i32 - color[i] as i32).pow(2))
+                .sum::<i32>()
+        };
+        (0..self.colors.len())
+            .min_by_key(|&i| distance(&self.colors[i]))
+            .unwrap_or(0) as u8
+    }
+
+    fn into_rgb(self) -> Vec<u8> {
+        // GIF palettes need at least two entries
+        let mut rgb: Vec<u8> = self.colors.into_iter().flatten().collect();
+        rgb.resize(rgb.len().max(6), 0);
+        rgb
+    }
+}
+
+fn blend(a: [u8; 3], b: [u8; 3]) -> [u8; 3] {
+    [0, 1, 2].map(|i| ((a[i] as u16 + b[i] as u16) / 2) as u8)
+}
+
+/// Resolves a terminal color to RGB using the xterm default palette.
+fn rgb(color: Color, default: [u8; 3]) -> [u8; 3] {
+    color::rgb(color).unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use ratatui::layout::Rect;
+
+    fn record(format: ExportFormat) -> (Vec<u8>, ExportStats) {
+        let mut config = Config::default();
+        config.scroll.speed = 20.0;
+        let app = App::new("Hi".to_string(), &config).unwrap();
+        let options = ExportOptions {
+            format,
+            width: 20,
+            height: 6,
+            fps: 10,
+            depth: ColorDepth::TrueColor,
+        };
+        let mut output = Vec::new();
+        let stats = export(app, &options, &mut output).unwrap();
+        (output, stats)
+    }
+
+    #[test]
+    fn asciicasts_have_a_header_and_one_redraw_per_changed_frame() {
+        let (output, stats) = record(ExportFormat::Cast);
+        let output = String::from_utf8(output).unwrap();
+        let mut lines = output.lines();
+        assert_eq!(
+            lines.next(),
+            Some(r#"{"env":{"TERM":"xterm-256color"},"height":6,"version":2,"width":20}"#)
+        );
+
+        let events: Vec<(f64, String)> = lines
+            .map(|line| {
+                let (time, kind, data): (f64, String, String) = serde_json::from_str(line).unwrap();
+                assert_eq!(kind, "o");
+                (time, data)
+            })
+            .collect();
+        assert!(!stats.truncated);
+        assert!(stats.frames > 1);
+        assert_eq!(events.len(), stats.frames + 1);
+        assert!(events.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(events[0].0, 0.0);
+
+        let (first, frames) = events[..stats.frames].split_first().unwrap();
+        assert!(
+            first.1.starts_with("\x1b[?25l\x1b[2J\x1b[H"),
+            "{:?}",
+            first.1
+        );
+        for (_, data) in frames {
+            assert!(
+                data.starts_with("\x1b[H") && !data.contains("\x1b[2J"),
+                "{data:?}"
+            );
+            assert_eq!(data.matches("\r\n").count(), 5, "{data:?}");
+            assert!(!data.replace("\r\n", "").contains('\n'), "{data:?}");
+        }
+        let (end, last) = events.last().unwrap();
+        assert_eq!(last, "\x1b[0m\x1b[?25h");
+        assert!((end - stats.duration.as_secs_f64()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn gifs_are_cell_sized_animations() {
+        let (output, stats) = record(ExportFormat::Gif);
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(output.as_slice())
+            .unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (20 * 8, 6 * 16));
+        let mut frames = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            frames += 1;
+        }
+        assert_eq!(frames, stats.frames);
+    }
+
+    #[test]
+    fn sextants_decode_to_their_six_cells() {
+        // Named after the cells they fill, numbered left to right and top to
+        // bottom; the left half (135) and right half (246) are skipped
+        for (c, cells) in [
+            ('\u{1FB00}', "1"),
+            ('\u{1FB13}', "35"),
+            ('\u{1FB14}', "235"),
+            ('\u{1FB27}', "146"),
+            ('\u{1FB28}', "1246"),
+            ('\u{1FB3B}', "23456"),
+        ] {
+            let bits = cells
+                .chars()
+                .map(|cell| 1 << (cell.to_digit(10).unwrap() - 1))
+                .sum::<u8>();
+            assert_eq!(sextant(c), Some(bits), "{c}");
+        }
+        assert_eq!(sextant('\u{1FAFF}'), None);
+        assert_eq!(sextant('\u{1FB3C}'), None);
+    }
+
+    #[test]
+    fn sextant_cells_cover_a_third_of_the_cell_height() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 1, 1));
+        buffer[(0, 0)].set_char('\u{1FB13}').set_fg(Color::Red);
+        let (pixels, palette) = rasterize(&buffer);
+
+        // The foreground is seen first, so it gets index 0
+        assert_eq!(&palette[..6], [205, 0, 0, 0, 0, 0]);
+        let pixel = |x: usize, y: usize| pixels[y * CELL_WIDTH + x];
+        // Cells 3 and 5: the left half of the middle and bottom thirds
+        assert_eq!(pixel(0, 0), 1);
+        assert_eq!(pixel(3, 5), 1);
+        assert_eq!(pixel(0, 6), 0);
+        assert_eq!(pixel(3, 15), 0);
+        assert_eq!(pixel(4, 6), 1);
+        assert_eq!(pixel(7, 15), 1);
+    }
+}
@@ -1,17 +1,34 @@
 //! Text input handling from various sources.
 
+use crate::app::{LineKind, ScriptLine};
+use crate::config::{DisplayConfig, OtherLines, ScriptConfig, ScrollConfig};
 use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::io::{self, Read};
 use std::path::Path;
+use std::time::Duration;
 
 /// Indicates where the text content came from.
-#[allow(dead_code)]
 pub enum TextSource {
     Stdin,
     File(String),
     Editor,
 }
 
+impl TextSource {
+    /// Returns true if the source is a file with a Fountain extension.
+    pub fn is_fountain(&self) -> bool {
+        match self {
+            TextSource::File(path) => Path::new(path)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("fountain")),
+            _ => false,
+        }
+    }
+}
+
 /// Gets text content from the appropriate source.
 ///
 /// Priority: stdin (if piped) > file argument > interactive editor
@@ -30,6 +47,12 @@ pub fn get_text_content(file_path: Option<&Path>) -> Result<(String, TextSource)
     Ok((content, TextSource::Editor))
 }
 
+/// Reads a script file, even when stdin is piped.
+pub fn read_script_file(path: &Path) -> Result<(String, TextSource)> {
+    let content = read_from_file(path)?;
+    Ok((content, TextSource::File(path.display().to_string())))
+}
+
 fn read_from_stdin() -> Result<String> {
     let mut content = String::new();
     io::stdin()
@@ -60,3 +83,638 @@ fn open_editor() -> Result<String> {
 
     Ok(content)
 }
+
+/// Settings for one script, given at its top between `+++` lines (TOML) or
+/// `---` lines (YAML):
+///
+/// ```toml
+/// +++
+/// title = "Keynote"
+/// speaker = "ALICE"
+/// duration = "4m30s"
+///
+/// [display]
+/// font_scale = "full"
+/// +++
+/// ```
+///
+/// Besides `title`, `speaker` and `duration`, front matter can hold any of the
+/// config file's sections apart from profiles.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrontMatter {
+    /// Shown in the status bar
+    pub title: Option<String>,
+    /// How long the script should take to scroll through, which sets the speed
+    pub duration: Option<Duration>,
+    /// The settings, laid out like the config file; `speaker` is moved to
+    /// `script.speaker`
+    pub settings: toml::Table,
+}
+
+/// The keys front matter may have, for checking it before its settings are
+/// merged into the config.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(dead_code)] // only parsed to report mistakes
+struct FrontMatterKeys {
+    title: Option<String>,
+    speaker: Option<String>,
+    duration: Option<DurationValue>,
+    display: Option<DisplayConfig>,
+    scroll: Option<ScrollConfig>,
+    script: Option<ScriptConfig>,
+    speakers: Option<BTreeMap<String, String>>,
+}
+
+/// A duration given as seconds or as text such as "4m30s" or "4:30".
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum DurationValue {
+    Seconds(u64),
+    Text(String),
+}
+
+/// Splits front matter off the top of a script, returning it and the rest of
+/// the script.
+///
+/// Content that doesn't start with a `+++` or `---` line, or that never closes
+/// it, has no front matter. Neither does a `---` block that isn't a YAML
+/// mapping, such as Markdown text between two horizontal rules. Errors give
+/// line numbers within the whole script.
+pub fn split_front_matter(content: &str) -> Result<(Option<FrontMatter>, &str)> {
+    let content_start = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let Some((first, rest)) = content_start.split_once('\n') else {
+        return Ok((None, content));
+    };
+    let delimiter = first.trim_end();
+    if delimiter != "+++" && delimiter != "---" {
+        return Ok((None, content));
+    }
+
+    let mut offset = 0;
+    let mut body_end = None;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == delimiter {
+            body_end = Some((offset, offset + line.len()));
+            break;
+        }
+        offset += line.len();
+    }
+    let Some((end, after)) = body_end else {
+        return Ok((None, content));
+    };
+
+    // A blank line in place of the opening delimiter keeps line numbers in
+    // errors the same as in the script
+    let source = format!("\n{}", &rest[..end]);
+    let (keys, table) = if delimiter == "+++" {
+        let keys: FrontMatterKeys =
+            toml::from_str(&source).context("Invalid front matter (TOML)")?;
+        (keys, source.parse::<toml::Table>()?)
+    } else if rest[..end].trim().is_empty() {
+        (serde_yaml::from_str("{}")?, toml::Table::new())
+    } else {
+        if !serde_yaml::from_str::<serde_yaml::Value>(&source).is_ok_and(|v| v.is_mapping()) {
+            return Ok((None, content));
+        }
+        let keys: FrontMatterKeys =
+            serde_yaml::from_str(&source).context("Invalid front matter (YAML)")?;
+        let table = serde_yaml::from_str(&source).context("Invalid front matter (YAML)")?;
+        (keys, table)
+    };
+
+    let front_matter = FrontMatter::from_parts(keys.title, keys.duration, table)
+        .context("Invalid front matter")?;
+    Ok((Some(front_matter), &rest[after..]))
+}
+
+impl FrontMatter {
+    /// Builds front matter from its already checked keys and the same keys as
+    /// a table, taking `title`, `duration` and `speaker` out of the settings.
+    pub(crate) fn from_parts(
+        title: Option<String>,
+        duration: Option<DurationValue>,
+        mut table: toml::Table,
+    ) -> Result<Self> {
+        let duration = match duration {
+            Some(DurationValue::Seconds(seconds)) => Some(Duration::from_secs(seconds)),
+            Some(DurationValue::Text(text)) => {
+                Some(parse_duration(&text).map_err(|e| anyhow::anyhow!("duration: {}", e))?)
+            }
+            None => None,
+        };
+        if duration == Some(Duration::ZERO) {
+            anyhow::bail!("duration must be longer than zero");
+        }
+        table.remove("title");
+        table.remove("duration");
+        if let Some(speaker) = table.remove("speaker") {
+            let script = table
+                .entry("script")
+                .or_insert_with(|| toml::Table::new().into());
+            if let Some(script) = script.as_table_mut() {
+                script.insert("speaker".to_string(), speaker);
+            }
+        }
+        Ok(Self {
+            title,
+            duration,
+            settings: table,
+        })
+    }
+}
+
+/// Parses a duration such as "90", "90s", "4m30s", "1h", "4:30" or "1:04:30".
+/// Plain numbers are seconds.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let invalid = || {
+        format!(
+            "'{}' is not a duration (expected e.g. 90, 4m30s, 1h or 4:30)",
+            text
+        )
+    };
+    if text.contains(':') {
+        let mut seconds = 0u64;
+        let parts: Vec<_> = text.split(':').collect();
+        if parts.len() > 3 {
+            return Err(invalid());
+        }
+        for part in parts {
+            let part: u64 = part.parse().map_err(|_| invalid())?;
+            seconds = seconds
+                .checked_mul(60)
+                .and_then(|seconds| seconds.checked_add(part))
+                .ok_or_else(invalid)?;
+        }
+        return Ok(Duration::from_secs(seconds));
+    }
+    if let Ok(seconds) = text.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).map_err(|_| invalid());
+    }
+
+    let mut seconds = 0.0;
+    let mut number = String::new();
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            'h' => 3600.0,
+            'm' => 60.0,
+            's' => 1.0,
+            _ => return Err(invalid()),
+        };
+        let value: f64 = number.parse().map_err(|_| invalid())?;
+        seconds += value * unit;
+        number.clear();
+    }
+    if !number.is_empty() || text.is_empty() {
+        return Err(invalid());
+    }
+    Duration::try_from_secs_f64(seconds).map_err(|_| invalid())
+}
+
+/// A structural element of a Fountain screenplay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FountainElement {
+    SceneHeading(String),
+    Action(String),
+    /// Character cue, with extensions such as `(V.O.)` removed
+    Character(String),
+    Parenthetical(String),
+    Dialogue(String),
+    Transition(String),
+    /// Paragraph break between blocks
+    Break,
+}
+
+/// Parses Fountain screenplay text into its structural elements.
+///
+/// Title pages, notes, boneyard, sections, synopses and page breaks are dropped.
+pub fn parse_fountain(content: &str) -> Vec<FountainElement> {
+    let content = strip_fountain_comments(content);
+    let lines: Vec<&str> = skip_title_page(&content).lines().collect();
+
+    let mut elements = Vec::new();
+    let mut in_dialogue = false;
+
+    for (i, raw) in lines.iter().enumerate() {
+        let line = raw.trim();
+        let prev_blank = i == 0 || lines[i - 1].trim().is_empty();
+        let next_blank = lines.get(i + 1).is_none_or(|next| next.trim().is_empty());
+
+        if line.is_empty() {
+            if elements
+                .last()
+                .is_some_and(|e| *e != FountainElement::Break)
+            {
+                elements.push(FountainElement::Break);
+            }
+            in_dialogue = false;
+            continue;
+        }
+
+        if in_dialogue {
+            if line.starts_with('(') && line.ends_with(')') {
+                elements.push(FountainElement::Parenthetical(line.to_string()));
+            } else {
+                elements.push(FountainElement::Dialogue(line.to_string()));
+            }
+            continue;
+        }
+
+        let element = if let Some(action) = line.strip_prefix('!') {
+            FountainElement::Action(action.trim().to_string())
+        } else if line.starts_with('#') || line.starts_with('=') {
+            continue;
+        } else if let Some(heading) = line.strip_prefix('.').filter(|_| !line.starts_with("..")) {
+            FountainElement::SceneHeading(strip_scene_number(heading))
+        } else if prev_blank && is_scene_heading(line) {
+            FountainElement::SceneHeading(strip_scene_number(line))
+        } else if let Some(centered) = line.strip_prefix('>').and_then(|l| l.strip_suffix('<')) {
+            FountainElement::Action(centered.trim().to_string())
+        } else if let Some(transition) = line.strip_prefix('>') {
+            FountainElement::Transition(transition.trim().to_string())
+        } else if prev_blank && next_blank && is_uppercase(line) && line.ends_with("TO:") {
+            FountainElement::Transition(line.to_string())
+        } else if prev_blank && !next_blank && (line.starts_with('@') || is_uppercase(line)) {
+            in_dialogue = true;
+            FountainElement::Character(character_name(line))
+        } else {
+            FountainElement::Action(line.to_string())
+        };
+
+        elements.push(element);
+    }
+
+    if elements.last() == Some(&FountainElement::Break) {
+        elements.pop();
+    }
+
+    elements
+}
+
+/// Returns the unique character names in order of first appearance.
+pub fn fountain_characters(elements: &[FountainElement]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for element in elements {
+        if let FountainElement::Character(name) = element
+            && !names.contains(name)
+        {
+            names.push(name.clone());
+        }
+    }
+    names
+}
+
+/// Turns parsed Fountain elements into prompt lines.
+///
+/// Without a character every element is shown as regular text. With a character,
+/// only that character's dialogue is prompted: their parentheticals are dimmed,
+/// everything else is dimmed or hidden according to `others`, and scene headings
+/// are always kept as section markers.
+pub fn fountain_lines(
+    elements: &[FountainElement],
+    character: Option<&str>,
+    others: OtherLines,
+) -> Vec<ScriptLine> {
+    let mut lines: Vec<ScriptLine> = Vec::new();
+    let mut speaker: Option<&str> = None;
+
+    let other_kind = LineKind::for_others(others);
+
+    for element in elements {
+        let is_selected = |name: Option<&str>| match (character, name) {
+            (Some(wanted), Some(name)) => wanted.eq_ignore_ascii_case(name),
+            _ => false,
+        };
+
+        let (text, kind) = match element {
+            FountainElement::Break => {
+                speaker = None;
+                if lines.last().is_some_and(|l| !l.text.is_empty()) {
+                    lines.push(ScriptLine::default());
+                }
+                continue;
+            }
+            FountainElement::SceneHeading(text) => (text, Some(LineKind::Marker)),
+            FountainElement::Character(name) => {
+                speaker = Some(name);
+                match character {
+                    None => (name, Some(LineKind::Normal)),
+                    // The selected character's own cue is implied by their dialogue
+                    Some(_) if is_selected(speaker) => continue,
+                    Some(_) => (name, other_kind),
+                }
+            }
+            FountainElement::Dialogue(text) => match character {
+                None => (text, Some(LineKind::Normal)),
+                Some(_) if is_selected(speaker) => (text, Some(LineKind::Normal)),
+                Some(_) => (text, other_kind),
+            },
+            FountainElement::Parenthetical(text) => match character {
+                None => (text, Some(LineKind::Normal)),
+                Some(_) if is_selected(speaker) => (text, Some(LineKind::Dimmed)),
+                Some(_) => (text, other_kind),
+            },
+            FountainElement::Action(text) | FountainElement::Transition(text) => match character {
+                None => (text, Some(LineKind::Normal)),
+                Some(_) => (text, other_kind),
+            },
+        };
+
+        if let Some(kind) = kind {
+            lines.push(ScriptLine::new(text.as_str(), kind));
+        }
+    }
+
+    while lines.last().is_some_and(|l| l.text.is_empty()) {
+        lines.pop();
+    }
+
+    lines
+}
+
+/// Removes `/* boneyard */` and `[[notes]]`, which may span several lines.
+/// An opening `/*` or `[[` that is never closed is kept as text.
+fn strip_fountain_comments(content: &str) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut rest = content;
+
+    loop {
+        let boneyard = rest.find("/*").map(|i| (i, "*/"));
+        let note = rest.find("[[").map(|i| (i, "]]"));
+        let next = match (boneyard, note) {
+            (Some(b), Some(n)) => Some(if b.0 < n.0 { b } else { n }),
+            (b, n) => b.or(n),
+        };
+
+        let Some((start, terminator)) = next else {
+            result.push_str(rest);
+            break;
+        };
+
+        match rest[start + 2..].find(terminator) {
+            Some(end) => {
+                result.push_str(&rest[..start]);
+                rest = &rest[start + 2 + end + 2..];
+            }
+            None => {
+                result.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+            }
+        }
+    }
+
+    result
+}
+
+/// Skips the `Key: value` title page block at the start of the document, if any.
+fn skip_title_page(content: &str) -> &str {
+    let trimmed = content.trim_start_matches(['\n', '\r']);
+    let first_line = trimmed.lines().next().unwrap_or("");
+    let is_title_key = first_line.split_once(':').is_some_and(|(key, _)| {
+        !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == ' ')
+    });
+
+    if !is_title_key {
+        return content;
+    }
+
+    match trimmed.find("\n\n").or_else(|| trimmed.find("\r\n\r\n")) {
+        Some(end) => &trimmed[end..],
+        None => "",
+    }
+}
+
+fn is_scene_heading(line: &str) -> bool {
+    let upper = line.to_uppercase();
+    ["INT./EXT", "INT/EXT", "I/E", "INT", "EXT", "EST"]
+        .iter()
+        .any(|prefix| {
+            upper
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with('.') || rest.starts_with(' '))
+        })
+}
+
+/// Removes a trailing `#1A#` scene number from a heading.
+fn strip_scene_number(heading: &str) -> String {
+    let heading = heading.trim();
+    if heading.ends_with('#')
+        && let Some(start) = heading[..heading.len() - 1].rfind('#')
+    {
+        return heading[..start].trim_end().to_string();
+    }
+    heading.to_string()
+}
+
+/// Returns true if the line has letters and none of them are lowercase,
+/// ignoring any parenthetical extension such as `(cont'd)`.
+fn is_uppercase(line: &str) -> bool {
+    let name = line.split('(').next().unwrap_or(line);
+    name.chars().any(char::is_alphabetic) && !name.chars().any(char::is_lowercase)
+}
+
+/// Extracts a character name from a cue like `@McCLANE (V.O.) ^`.
+fn character_name(cue: &str) -> String {
+    let cue = cue.trim_start_matches('@').trim_end_matches('^');
+    cue.split('(').next().unwrap_or(cue).trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toml_and_yaml_front_matter_are_split_off() {
+        let toml = "+++\ntitle = \"Keynote\"\nspeaker = \"ALICE\"\nduration = \"4m30s\"\n\n[scroll]\nspeed = 3.0\n+++\nALICE: Hello\n";
+        let yaml = "---\ntitle: Keynote\nspeaker: ALICE\nduration: 270\nscroll:\n  speed: 3.0\n---\nALICE: Hello\n";
+        for script in [toml, yaml] {
+            let (front_matter, rest) = split_front_matter(script).unwrap();
+            let front_matter = front_matter.unwrap();
+            assert_eq!(rest, "ALICE: Hello\n");
+            assert_eq!(front_matter.title.as_deref(), Some("Keynote"));
+            assert_eq!(front_matter.duration, Some(Duration::from_secs(270)));
+            assert_eq!(
+                front_matter.settings.to_string(),
+                "[scroll]\nspeed = 3.0\n\n[script]\nspeaker = \"ALICE\"\n"
+            );
+        }
+    }
+
+    #[test]
+    fn scripts_without_closed_front_matter_are_left_alone() {
+        for script in [
+            "Hello\n+++\n",
+            "---\nJust a rule above the text\n",
+            "+++",
+            "---\n\nA paragraph between rules.\n\n---\n\nMore text\n",
+            "---\n- a list\n- between rules\n---\n",
+        ] {
+            let (front_matter, rest) = split_front_matter(script).unwrap();
+            assert_eq!(front_matter, None);
+            assert_eq!(rest, script);
+        }
+    }
+
+    #[test]
+    fn front_matter_errors_give_the_script_line() {
+        let error = split_front_matter("+++\ntitle = \"A\"\nsped = 2\n+++\ntext\n").unwrap_err();
+        let message = format!("{:#}", error);
+        assert!(message.contains("line 3"), "{message}");
+        assert!(message.contains("unknown field `sped`"), "{message}");
+    }
+
+    const SCREENPLAY: &str = "\
+Title: The Test
+Author: Someone
+
+INT. HOUSE - NIGHT #1#
+
+Rain hammers the windows. [[fix this]]
+
+BOB (V.O.)
+(quietly)
+It's late.
+
+ALICE ^
+Too late.
+
+@McCLANE
+Yippee.
+
+/* cut
+this scene */
+CUT TO:
+
+.FLASHBACK
+
+!LOUD NOISES
+
+# Act Two
+
+= The end approaches
+
+> FADE OUT.
+
+> THE END <
+";
+
+    #[test]
+    fn fountain_elements_are_recognized() {
+        use FountainElement::*;
+        let text = |s: &str| s.to_string();
+        assert_eq!(
+            parse_fountain(SCREENPLAY),
+            [
+                SceneHeading(text("INT. HOUSE - NIGHT")),
+                Break,
+                Action(text("Rain hammers the windows.")),
+                Break,
+                Character(text("BOB")),
+                Parenthetical(text("(quietly)")),
+                Dialogue(text("It's late.")),
+                Break,
+                Character(text("ALICE")),
+                Dialogue(text("Too late.")),
+                Break,
+                Character(text("McCLANE")),
+                Dialogue(text("Yippee.")),
+                Break,
+                Transition(text("CUT TO:")),
+                Break,
+                SceneHeading(text("FLASHBACK")),
+                Break,
+                Action(text("LOUD NOISES")),
+                Break,
+                Transition(text("FADE OUT.")),
+                Break,
+                Action(text("THE END")),
+            ]
+        );
+        assert_eq!(
+            fountain_characters(&parse_fountain(SCREENPLAY)),
+            ["BOB", "ALICE", "McCLANE"]
+        );
+    }
+
+    #[test]
+    fn unclosed_notes_and_boneyard_are_kept() {
+        assert_eq!(
+            strip_fountain_comments("a [[note]] b /* bone\nyard */ c"),
+            "a  b  c"
+        );
+        for text in [
+            "Keep [[this\nand that",
+            "Keep /* this\nand that",
+            "[[ x /* y",
+        ] {
+            assert_eq!(strip_fountain_comments(text), text);
+        }
+    }
+
+    #[test]
+    fn fountain_lines_prompt_the_selected_character() {
+        let elements = parse_fountain(SCREENPLAY);
+        let lines = |character, others| -> Vec<(String, LineKind)> {
+            fountain_lines(&elements, character, others)
+                .into_iter()
+                .filter(|line| !line.text.is_empty())
+                .map(|line| (line.text, line.kind))
+                .take(6)
+                .collect()
+        };
+        let line = |text: &str, kind| (text.to_string(), kind);
+
+        assert!(
+            lines(None, OtherLines::Dim)
+                .iter()
+                .skip(1)
+                .all(|(_, kind)| *kind == LineKind::Normal)
+        );
+        assert_eq!(
+            lines(Some("bob"), OtherLines::Dim),
+            [
+                line("INT. HOUSE - NIGHT", LineKind::Marker),
+                line("Rain hammers the windows.", LineKind::Dimmed),
+                line("(quietly)", LineKind::Dimmed),
+                line("It's late.", LineKind::Normal),
+                line("ALICE", LineKind::Dimmed),
+                line("Too late.", LineKind::Dimmed),
+            ]
+        );
+        assert_eq!(
+            lines(Some("ALICE"), OtherLines::Hide),
+            [
+                line("INT. HOUSE - NIGHT", LineKind::Marker),
+                line("Too late.", LineKind::Normal),
+                line("FLASHBACK", LineKind::Marker),
+            ]
+        );
+    }
+
+    #[test]
+    fn durations_parse() {
+        for (text, seconds) in [
+            ("90", 90),
+            ("90s", 90),
+            ("4m30s", 270),
+            ("4m 30s", 270),
+            ("1h", 3600),
+            ("4:30", 270),
+            ("1:04:30", 3870),
+        ] {
+            assert_eq!(
+                parse_duration(text),
+                Ok(Duration::from_secs(seconds)),
+                "{text}"
+            );
+        }
+        for text in ["", "4x", "m", "1:2:3:4", "-5", "18446744073709551615:0"] {
+            assert!(parse_duration(text).is_err(), "{text}");
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! # tui_prompter
+//!
+//! The teleprompter engine behind `tp`, usable from any ratatui application.
+//!
+//! [`App`] holds the script, scroll position and display settings, and the
+//! [`Teleprompter`] widget draws it as scrolling big text:
+//!
+//! ```no_run
+//! use ratatui::{Terminal, backend::CrosstermBackend};
+//! use tui_prompter::{App, Teleprompter, config::Config};
+//!
+//! let mut app = App::new("Hello from tp!".to_string(), &Config::default()).unwrap();
+//! let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stdout())).unwrap();
+//!
+//! loop {
+//!     app.update();
+//!     terminal
+//!         .draw(|frame| frame.render_stateful_widget(Teleprompter, frame.area(), &mut app))
+//!         .unwrap();
+//!     if app.should_quit {
+//!         break;
+//!     }
+//! }
+//! ```
+
+pub mod app;
+pub mod clock;
+pub mod color;
+pub mod config;
+pub mod event;
+pub mod export;
+pub mod input;
+pub mod options;
+pub mod playlist;
+pub mod raster;
+pub mod snapshot;
+pub mod theme;
+pub mod ui;
+pub mod wrap;
+
+pub use app::App;
+pub use ui::Teleprompter;
+pub use wrap::wrap_text;
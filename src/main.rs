@@ -1,83 +1,686 @@
 //! # tp - Terminal Teleprompter
 //!
 //! A terminal-based teleprompter application that displays scrolling text
-//! using large, readable characters.
+//! using large, readable characters. This binary is a thin front end over the
+//! `tui_prompter` library.
 
-mod app;
 mod cli;
-mod config;
-mod event;
-mod input;
-mod ui;
-
-use anyhow::Result;
-use crossterm::{
-    execute,
-    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+mod terminal;
+mod watch;
+
+use anyhow::{Context, Result};
+use ratatui::{
+    Terminal, TerminalOptions, Viewport,
+    backend::{Backend, CrosstermBackend},
 };
-use ratatui::{Terminal, backend::CrosstermBackend};
 use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use app::App;
-use clap::Parser;
-use cli::Args;
-use config::Config;
-use input::get_text_content;
+use clap::{Parser, ValueEnum};
+use cli::{Args, Command, ConfigCommand, ExportArgs, RenderArgs, RenderFormat};
+use terminal::{Signals, TerminalGuard};
+use tui_prompter::App;
+use tui_prompter::app::{SegmentChange, SegmentPosition};
+use tui_prompter::clock::MonotonicClock;
+use tui_prompter::color::ColorDepth;
+use tui_prompter::config::{self, Config, Layers, Source, Sources};
+use tui_prompter::export::{self, ExportFormat, ExportOptions};
+// `cli` refers to the shared option types as `crate::options`, which also
+// works when build.rs includes it
+use tui_prompter::input::{
+    FrontMatter, fountain_characters, fountain_lines, get_text_content, parse_fountain,
+    read_script_file, split_front_matter,
+};
+use tui_prompter::options;
+use tui_prompter::playlist::{Playlist, Segment};
+use tui_prompter::theme::Theme;
+use tui_prompter::{event, snapshot, ui};
+use watch::FileWatcher;
 
 fn main() -> Result<()> {
     let args = Args::parse();
-    let mut config = Config::load().unwrap_or_default();
+    for warning in Layers::default().push_env(env_vars()) {
+        eprintln!("Warning: {}", warning);
+    }
+
+    match &args.command {
+        Some(Command::Render(render)) => {
+            let playlist = single_script(render.file.as_slice(), "render")?;
+            let (mut app, _) = load_app(&args, &playlist, 0)?;
+            return print_frame(&mut app, render);
+        }
+        Some(Command::Export(export)) => {
+            let playlist = single_script(export.file.as_slice(), "export")?;
+            let (app, _) = load_app(&args, &playlist, 0)?;
+            return export_recording(app, export);
+        }
+        Some(Command::Config(config)) => return config_command(&args, &config.action),
+        None => {}
+    }
+
+    let playlist = Playlist::from_args(&args.files)?;
+
+    if let Some(format) = args.print {
+        for index in 0..playlist.segments.len() {
+            let (mut app, _) = load_app(&args, &playlist, index)?;
+            print_banner(&mut app, format, args.width)?;
+        }
+        return Ok(());
+    }
+
+    let (mut app, mut settings) = load_app(&args, &playlist, 0)?;
+    for index in 1..playlist.segments.len() {
+        check_segment(&playlist, index)?;
+    }
+
+    // Register the handlers before raw mode so a signal can't arrive between
+    // the two and kill tp with the terminal still in raw mode
+    let signals = Signals::register()?;
+    let inline = args.inline.is_some();
+    let mut guard = TerminalGuard::enter(inline)?;
+    terminal::install_panic_hook(inline);
+
+    // Use /dev/tty directly so TUI works even when stdin is piped
+    let tty = File::options().read(true).write(true).open("/dev/tty")?;
+    let viewport = match args.inline {
+        Some(rows) => Viewport::Inline(rows),
+        None => Viewport::Fullscreen,
+    };
+    let backend = CrosstermBackend::new(tty);
+    let mut terminal = Terminal::with_options(backend, TerminalOptions { viewport })?;
+
+    let min_frame = Duration::from_secs_f64(1.0 / args.fps as f64);
+    let depth = ColorDepth::detect();
+    let result = run_app(
+        &mut terminal,
+        &mut app,
+        &mut guard,
+        &signals,
+        &mut settings,
+        &playlist,
+        (min_frame, depth),
+    );
+
+    if inline {
+        // Leave the shell prompt where the strip was
+        terminal.clear()?;
+    }
+    drop(guard);
+
+    result?;
+    if args.save_on_exit {
+        let path = settings.save(&app)?;
+        eprintln!("Saved settings to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Longest time to block waiting for input while paused or idle.
+///
+/// This is a trade-off: crossterm can only block on the terminal, not on a
+/// signal self-pipe as well, so signals are flags looked at after each wait,
+/// and the config watcher needs a timer anyway. Waking four times a second
+/// without redrawing, and looking at the watched files on every other wake,
+/// keeps quit and suspend signals answered within a quarter second for next
+/// to no CPU, without a second thread feeding events to the loop.
+const IDLE_POLL: Duration = Duration::from_millis(250);
+
+/// How long a config error stays on screen.
+const ERROR_OVERLAY: Duration = Duration::from_secs(8);
+
+/// Runs the prompter until it quits, redrawing only when the text moves, a key
+/// is handled or the terminal changes, and never more often than `min_frame`.
+/// Frames are downsampled to the terminal's color `depth`. Edits to the config
+/// file are applied as they are saved, and the app is replaced by another
+/// segment of the `playlist` when it asks to move.
+fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    guard: &mut TerminalGuard,
+    signals: &Signals,
+    settings: &mut Settings,
+    playlist: &Playlist,
+    (min_frame, depth): (Duration, ColorDepth),
+) -> Result<()> {
+    let mut needs_redraw = true;
+    let mut drawn_row = None;
+
+    loop {
+        if signals.quit_requested() {
+            break;
+        }
+        if signals.take_suspend() || app.should_suspend {
+            app.should_suspend = false;
+            terminal::suspend_process(guard)?;
+        }
+        if app.should_save {
+            app.should_save = false;
+            app.notice = Some(match settings.save(app) {
+                Ok(path) => format!("Saved to {}", path.display()),
+                Err(e) => format!("Not saved: {:#}", e),
+            });
+        }
+        if settings.watcher.changed() {
+            match settings.reload(app) {
+                Ok(()) => {
+                    app.notice
+                        .get_or_insert_with(|| "Config reloaded".to_string());
+                }
+                Err(e) => app.show_overlay(
+                    format!("Config not reloaded, keeping the last good one:\n{:#}", e),
+                    ERROR_OVERLAY,
+                ),
+            }
+            needs_redraw = true;
+        }
+        if app.expire_overlay() {
+            needs_redraw = true;
+        }
+        if signals.take_resumed() {
+            // The screen may have been used by something else while we were stopped
+            terminal.clear()?;
+            app.skip_elapsed();
+            needs_redraw = true;
+        }
+
+        app.update();
+        if let Some(change) = app.segment_change.take() {
+            let index = app.segment.map_or(0, |segment| segment.index);
+            let index = match change {
+                SegmentChange::Next => index + 1,
+                SegmentChange::Previous => index.saturating_sub(1),
+            };
+            match load_app(settings.args, playlist, index) {
+                Ok((mut next, next_settings)) => {
+                    // Carry on scrolling, or stay paused, in the new segment,
+                    // keeping whatever was adjusted by hand
+                    next.keep_adjustments(app);
+                    next.paused = app.paused;
+                    *app = next;
+                    *settings = next_settings;
+                }
+                Err(e) => {
+                    // Stay on this segment. Pausing keeps its end from asking
+                    // for the broken one again every frame; resuming retries
+                    app.paused = true;
+                    app.show_overlay(format!("Staying on this segment:\n{:#}", e), ERROR_OVERLAY);
+                }
+            }
+            needs_redraw = true;
+        }
+        if app.should_quit {
+            break;
+        }
+
+        if needs_redraw || drawn_row != Some(ui::scroll_row(app)) {
+            terminal.draw(|frame| {
+                ui::render(frame, app);
+                depth.downsample_buffer(frame.buffer_mut());
+            })?;
+            drawn_row = Some(ui::scroll_row(app));
+        }
+
+        // Sleep until the text next moves, or until input arrives
+        let timeout =
+            ui::next_frame(app).map_or(IDLE_POLL, |next| next.clamp(min_frame, IDLE_POLL));
+        needs_redraw = event::handle_events(app, timeout)?;
+
+        if app.should_quit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the config file given with `--config` or `TP_CONFIG`, or the
+/// default one.
+fn config_path(args: &Args) -> Result<PathBuf> {
+    match &args.config {
+        Some(path) => Ok(path.clone()),
+        None => Config::config_path(),
+    }
+}
+
+/// Loads the config file, applies the selected profile, the script's front
+/// matter, the settings its playlist entry `overrides`, `TP_*` environment
+/// variables and command-line overrides on top of it, and fills in the
+/// remaining colors from the theme.
+fn load_config(
+    args: &Args,
+    front_matter: Option<&FrontMatter>,
+    overrides: Option<&FrontMatter>,
+) -> Result<(Config, Sources)> {
+    let mut layers = Layers::default();
+    let path = config_path(args)?;
+    if path.exists() {
+        layers.push_file(&path)?;
+    } else if args.config.is_some() {
+        anyhow::bail!("Config file {} not found", path.display());
+    }
+    if let Some(profile) = &args.profile {
+        layers.push_profile(profile)?;
+    }
+    if let Some(front_matter) = front_matter {
+        layers.push(Source::FrontMatter, front_matter.settings.clone());
+    }
+    if let Some(overrides) = overrides {
+        layers.push(Source::Playlist, overrides.settings.clone());
+    }
+    // Invalid variables were warned about once at startup
+    layers.push_env(env_vars());
+    layers.push(Source::CommandLine, command_line_settings(args));
+    layers.resolve()
+}
+
+/// Returns the environment variables that may be settings. Variables that
+/// aren't valid UTF-8 can't be.
+fn env_vars() -> impl Iterator<Item = (String, String)> {
+    std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+}
+
+/// Returns the settings given on the command line, laid out like the config
+/// file.
+fn command_line_settings(args: &Args) -> toml::Table {
+    let mut display = toml::Table::new();
+    let mut scroll = toml::Table::new();
+    let mut script = toml::Table::new();
 
     if let Some(speed) = args.speed {
-        config.scroll.speed = speed;
+        scroll.insert("speed".into(), speed.into());
     }
     if let Some(scale) = args.scale {
-        config.display.font_scale = scale.clamp(1, 3);
+        display.insert("font_scale".into(), value_name(scale).into());
     }
-    if let Some(color) = args.color {
-        config.display.text_color = color;
+    if let Some(theme) = &args.theme {
+        display.insert("theme".into(), theme.clone().into());
     }
-    if let Some(background) = args.background {
-        config.display.background_color = background;
+    if let Some(color) = &args.color {
+        display.insert("text_color".into(), color.clone().into());
+    }
+    if let Some(background) = &args.background {
+        display.insert("background_color".into(), background.clone().into());
     }
     if let Some(padding) = args.padding {
-        config.display.horizontal_padding = padding.clamp(0, 40);
+        display.insert("horizontal_padding".into(), i64::from(padding).into());
+    }
+    if let Some(speaker) = &args.speaker {
+        script.insert("speaker".into(), speaker.clone().into());
+    }
+    if let Some(others) = args.others {
+        script.insert("others".into(), value_name(others).into());
     }
 
-    let (content, _source) = get_text_content(args.file.as_deref())?;
+    let mut settings = toml::Table::new();
+    for (name, section) in [("display", display), ("scroll", scroll), ("script", script)] {
+        if !section.is_empty() {
+            settings.insert(name.into(), section.into());
+        }
+    }
+    settings
+}
 
-    if content.trim().is_empty() {
-        anyhow::bail!("No content to display");
+/// Returns the name a value is given by on the command line.
+fn value_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .expect("no values are skipped")
+        .get_name()
+        .to_string()
+}
+
+/// Runs one of the `tp config` subcommands.
+fn config_command(args: &Args, action: &ConfigCommand) -> Result<()> {
+    let path = config_path(args)?;
+    match action {
+        ConfigCommand::Path => println!("{}", path.display()),
+        ConfigCommand::Init { force } => {
+            if path.exists() && !*force {
+                anyhow::bail!(
+                    "{} already exists (use --force to overwrite it)",
+                    path.display()
+                );
+            }
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("Failed to create {}", dir.display()))?;
+            }
+            std::fs::write(&path, config::DEFAULT_CONFIG)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!("Wrote {}", path.display());
+        }
+        ConfigCommand::Show { file } => {
+            let front_matter = read_front_matter(file.as_deref())?;
+            let (config, sources) = load_config(args, front_matter.as_ref(), None)?;
+            print!("{}", config.annotated(&sources));
+        }
+        ConfigCommand::Check { file } => {
+            let front_matter = read_front_matter(file.as_deref())?;
+            load_config(args, front_matter.as_ref(), None)?;
+            if path.exists() {
+                println!("{}: OK", path.display());
+            } else {
+                println!("No config file at {}; using defaults", path.display());
+            }
+            if let Some(file) = file {
+                println!("{}: OK", file.display());
+            }
+        }
     }
+    Ok(())
+}
+
+/// Reads the front matter of a script file, if one is given.
+fn read_front_matter(file: Option<&Path>) -> Result<Option<FrontMatter>> {
+    let Some(file) = file else {
+        return Ok(None);
+    };
+    let content = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read file: {}", file.display()))?;
+    let (front_matter, _) = split_front_matter(&content)?;
+    Ok(front_matter)
+}
 
-    let mut app = App::new(content, &config);
+/// The config the prompter runs with, for saving the adjustments made while
+/// prompting and reloading the config file when it changes.
+struct Settings<'a> {
+    args: &'a Args,
+    front_matter: Option<FrontMatter>,
+    /// What the playlist sets for the script
+    overrides: FrontMatter,
+    config: Config,
+    sources: Sources,
+    watcher: FileWatcher,
+}
 
-    // Use /dev/tty directly so TUI works even when stdin is piped
-    let mut tty = File::options().read(true).write(true).open("/dev/tty")?;
+impl Settings<'_> {
+    /// Saves the app's speed, font size, padding and colors to the config file,
+    /// or to the profile in use. Returns the path saved to.
+    fn save(&self, app: &App) -> Result<PathBuf> {
+        let mut display = toml::Table::new();
+        let font_scale = match app.auto_fit {
+            Some(_) => "auto",
+            None => app.font_scale.name(),
+        };
+        display.insert("font_scale".into(), font_scale.into());
+        display.insert(
+            "horizontal_padding".into(),
+            i64::from(app.horizontal_padding).into(),
+        );
+        // Colors from the theme, the script or the environment stay with them
+        display.extend(self.config.chosen_colors(&self.sources));
 
-    enable_raw_mode()?;
-    execute!(tty, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(tty);
-    let mut terminal = Terminal::new(backend)?;
+        let mut settings = toml::Table::new();
+        settings.insert("display".into(), display.into());
+        // A speed worked out from the script's duration only suits that script
+        if app.target_duration.is_none() {
+            let mut scroll = toml::Table::new();
+            let speed = (app.speed * 100.0).round() / 100.0;
+            scroll.insert("speed".into(), speed.into());
+            settings.insert("scroll".into(), scroll.into());
+        }
 
-    let result = run_app(&mut terminal, &mut app);
+        let path = config_path(self.args)?;
+        config::save_settings(&path, self.args.profile.as_deref(), &settings)?;
+        Ok(path)
+    }
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+    /// Loads the config again and applies whatever changed to the app. On
+    /// error the current config stays in effect.
+    fn reload(&mut self, app: &mut App) -> Result<()> {
+        let (config, sources) =
+            load_config(self.args, self.front_matter.as_ref(), Some(&self.overrides))?;
+        app.reconfigure(&self.config, &config)?;
+        self.watcher.watch(watched_files(self.args, &config)?);
+        self.config = config;
+        self.sources = sources;
+        Ok(())
+    }
+}
 
-    result
+/// Returns the files whose edits reload the config: the config file and the
+/// theme file it names, if the theme isn't built in.
+fn watched_files(args: &Args, config: &Config) -> Result<Vec<PathBuf>> {
+    let mut files = vec![config_path(args)?];
+    files.extend(config.display.theme.as_deref().and_then(Theme::file_path));
+    Ok(files)
 }
 
-fn run_app(terminal: &mut Terminal<CrosstermBackend<File>>, app: &mut App) -> Result<()> {
-    loop {
-        app.update();
-        terminal.draw(|frame| ui::render(frame, app))?;
-        event::handle_events(app)?;
+/// Returns the playlist for a subcommand that takes one script, which may be a
+/// playlist file listing a single one.
+fn single_script(file: &[PathBuf], command: &str) -> Result<Playlist> {
+    let playlist = Playlist::from_args(file)?;
+    if let ([file], count @ 2..) = (file, playlist.segments.len()) {
+        anyhow::bail!(
+            "tp {} takes a single script, but {} lists {}",
+            command,
+            file.display(),
+            count
+        );
+    }
+    Ok(playlist)
+}
 
-        if app.should_quit {
-            break;
+/// Loads the segment of the playlist at `index`, naming its file in errors
+/// when there are several.
+fn load_app<'a>(args: &'a Args, playlist: &Playlist, index: usize) -> Result<(App, Settings<'a>)> {
+    let segment = &playlist.segments[index];
+    let count = playlist.segments.len();
+    if count == 1 {
+        return load_script(args, segment, None, playlist.from_file);
+    }
+    load_script(args, segment, Some(SegmentPosition { index, count }), true)
+        .with_context(|| segment_name(playlist, index))
+}
+
+/// Checks that a later segment of the playlist can be read and that its front
+/// matter parses, so a missing file or a typo stops the show before it starts.
+/// The rest, such as its config and speakers, is only checked once the segment
+/// is reached, where a failure keeps the show on the current segment.
+fn check_segment(playlist: &Playlist, index: usize) -> Result<()> {
+    let segment = &playlist.segments[index];
+    if let Some(file) = &segment.file {
+        let (content, _) = read_script_file(file).with_context(|| segment_name(playlist, index))?;
+        split_front_matter(&content).with_context(|| segment_name(playlist, index))?;
+    }
+    Ok(())
+}
+
+/// Names a segment of a playlist in errors.
+fn segment_name(playlist: &Playlist, index: usize) -> String {
+    let file = playlist.segments[index].file.as_deref();
+    let file = file.unwrap_or(Path::new("-"));
+    let count = playlist.segments.len();
+    format!("Segment {}/{} ({})", index + 1, count, file.display())
+}
+
+/// Reads a script, loads the config with the script's front matter and its
+/// playlist entry, and builds the app, applying character and speaker
+/// selection. A script `listed` in a playlist is read from its file even with
+/// stdin piped.
+fn load_script<'a>(
+    args: &'a Args,
+    segment: &Segment,
+    position: Option<SegmentPosition>,
+    listed: bool,
+) -> Result<(App, Settings<'a>)> {
+    let (content, source) = match (&segment.file, listed) {
+        (Some(file), true) => read_script_file(file)?,
+        (file, _) => get_text_content(file.as_deref())?,
+    };
+    let (front_matter, content) = split_front_matter(&content)?;
+    let overrides = &segment.overrides;
+    let (loaded, sources) = load_config(args, front_matter.as_ref(), Some(overrides))?;
+    let config = &loaded;
+
+    if content.trim().is_empty() {
+        anyhow::bail!("No content to display");
+    }
+
+    let mut app = if args.fountain || source.is_fountain() {
+        let elements = parse_fountain(content);
+        if let Some(character) = &args.character {
+            let characters = fountain_characters(&elements);
+            if !characters.iter().any(|c| c.eq_ignore_ascii_case(character)) {
+                anyhow::bail!(
+                    "Character '{}' has no dialogue (characters: {})",
+                    character,
+                    characters.join(", ")
+                );
+            }
         }
+        let lines = fountain_lines(&elements, args.character.as_deref(), config.script.others);
+        App::from_lines(lines, config)?
+    } else {
+        if args.character.is_some() {
+            anyhow::bail!("--character requires a Fountain script (use --fountain)");
+        }
+        let app = App::new(content.to_string(), config)?;
+        // A speaker from the config file may not apply to every script, so only
+        // a speaker asked for on the command line, in the script's front
+        // matter or in the playlist is required to exist
+        let required = matches!(
+            sources.get("script.speaker"),
+            Some(Source::CommandLine | Source::FrontMatter | Source::Playlist)
+        );
+        if let Some(speaker) = config.script.speaker.as_ref().filter(|_| required)
+            && !app.speakers.iter().any(|s| s.eq_ignore_ascii_case(speaker))
+        {
+            if app.speakers.is_empty() {
+                anyhow::bail!(
+                    "Speaker '{}' not found: no `NAME: line` speakers detected",
+                    speaker
+                );
+            }
+            anyhow::bail!(
+                "Speaker '{}' not found (speakers: {})",
+                speaker,
+                app.speakers.join(", ")
+            );
+        }
+        app
+    };
+
+    let own = front_matter.as_ref();
+    app.title = overrides
+        .title
+        .clone()
+        .or_else(|| own.and_then(|front_matter| front_matter.title.clone()))
+        .or_else(|| {
+            let file = position.and(segment.file.as_ref())?;
+            Some(file.file_stem()?.to_string_lossy().into_owned())
+        });
+    // A speed given on the command line wins over any duration, and one given
+    // in the playlist over the script's own
+    let duration = match overrides.duration {
+        Some(duration) => Some(duration),
+        None if sources.get("scroll.speed") == Some(&Source::Playlist) => None,
+        None => own.and_then(|front_matter| front_matter.duration),
+    };
+    if args.speed.is_none() {
+        app.target_duration = duration;
+    }
+    app.segment = position;
+    let watcher = FileWatcher::new(watched_files(args, config)?, MonotonicClock);
+    let settings = Settings {
+        args,
+        front_matter,
+        overrides: overrides.clone(),
+        config: loaded,
+        sources,
+        watcher,
+    };
+    Ok((app, settings))
+}
+
+/// Renders a single frame at the requested offset and prints it to stdout.
+fn print_frame(app: &mut App, render: &RenderArgs) -> Result<()> {
+    app.scroll_offset = render.at;
+    let mut buffer = snapshot::render_to_buffer(app, render.size.width, render.size.height);
+    let output = match render.format {
+        RenderFormat::Text => snapshot::buffer_to_text(&buffer),
+        RenderFormat::Ansi => {
+            ColorDepth::detect().downsample_buffer(&mut buffer);
+            snapshot::buffer_to_ansi(&buffer)
+        }
+    };
+    io::stdout().write_all(output.as_bytes())?;
+    Ok(())
+}
+
+/// Prints every line as big text to stdout, like figlet.
+fn print_banner(app: &mut App, format: Option<RenderFormat>, width: Option<u16>) -> Result<()> {
+    let format = format.unwrap_or(if atty::is(atty::Stream::Stdout) {
+        RenderFormat::Ansi
+    } else {
+        RenderFormat::Text
+    });
+    let width = width
+        .or_else(|| crossterm::terminal::size().ok().map(|(columns, _)| columns))
+        .unwrap_or(80);
+
+    let depth = ColorDepth::detect();
+    let mut stdout = io::stdout().lock();
+    for mut buffer in snapshot::render_banner(app, width) {
+        let output = match format {
+            RenderFormat::Text => snapshot::buffer_to_text(&buffer),
+            RenderFormat::Ansi => {
+                depth.downsample_buffer(&mut buffer);
+                snapshot::buffer_to_ansi(&buffer)
+            }
+        };
+        stdout.write_all(output.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Records the app scrolling to the end and writes it to the export's output.
+fn export_recording(app: App, args: &ExportArgs) -> Result<()> {
+    let extension = match args.format {
+        ExportFormat::Cast => "cast",
+        ExportFormat::Gif => "gif",
+    };
+    let options = ExportOptions {
+        format: args.format,
+        width: args.size.width,
+        height: args.size.height,
+        fps: args.fps,
+        depth: ColorDepth::detect(),
+    };
+
+    let output = match (&args.output, &args.file) {
+        (Some(path), _) => path.clone(),
+        (None, Some(file)) => file.with_extension(extension),
+        (None, None) => PathBuf::from("tp").with_extension(extension),
+    };
+    // A file named with --output is the user's choice; one named after the
+    // script may be an earlier recording
+    if args.output.is_none() && !args.force && output.exists() {
+        anyhow::bail!(
+            "{} already exists (use --force to overwrite it, or --output to pick another file)",
+            output.display()
+        );
+    }
+
+    let stats = if output.as_os_str() == "-" {
+        export::export(app, &options, io::stdout().lock())?
+    } else {
+        let file = File::create(&output)
+            .with_context(|| format!("Failed to create {}", output.display()))?;
+        let stats = export::export(app, &options, BufWriter::new(file))?;
+        eprintln!(
+            "Wrote {} ({} frames, {:.1}s)",
+            output.display(),
+            stats.frames,
+            stats.duration.as_secs_f64()
+        );
+        stats
+    };
+
+    if stats.truncated {
+        eprintln!("Warning: recording was cut off after 4 hours");
     }
     Ok(())
 }
@@ -0,0 +1,273 @@
+//! Settings with a fixed set of values, shared by the config file and the
+//! command line.
+//!
+//! Besides the library, `build.rs` includes this module to generate the man
+//! page, so it only depends on serde and clap.
+
+use clap::ValueEnum;
+use clap::builder::PossibleValue;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Presentation of lines outside the selected character's or speaker's lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OtherLines {
+    /// Show them dimmed, for context
+    #[default]
+    Dim,
+    /// Show them one font scale smaller
+    Smaller,
+    /// Leave them out entirely
+    Hide,
+}
+
+/// Size of the big text, ordered from smallest to largest.
+///
+/// Most sizes are `tui_big_text::PixelSize` variants; `Double` and `Triple`
+/// are `Full` scaled up two and three times.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FontSize {
+    /// Sextant pixels: 4x3 cells per character
+    Sextant,
+    /// Quadrant pixels: 4x4 cells per character
+    Quadrant,
+    /// Third-height pixels: 8x3 cells per character
+    ThirdHeight,
+    /// Half-height pixels: 8x4 cells per character
+    #[default]
+    HalfHeight,
+    /// Half-width pixels: 4x8 cells per character
+    HalfWidth,
+    /// Full-cell pixels: 8x8 cells per character
+    Full,
+    /// Full scaled up twice: 16x16 cells per character
+    Double,
+    /// Full scaled up three times: 24x24 cells per character
+    Triple,
+}
+
+impl FontSize {
+    /// Every size, from smallest to largest.
+    pub const ALL: [FontSize; 8] = [
+        FontSize::Sextant,
+        FontSize::Quadrant,
+        FontSize::ThirdHeight,
+        FontSize::HalfHeight,
+        FontSize::HalfWidth,
+        FontSize::Full,
+        FontSize::Double,
+        FontSize::Triple,
+    ];
+
+    /// Returns the size's name as used in the config file and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            FontSize::Sextant => "sextant",
+            FontSize::Quadrant => "quadrant",
+            FontSize::ThirdHeight => "third-height",
+            FontSize::HalfHeight => "half-height",
+            FontSize::HalfWidth => "half-width",
+            FontSize::Full => "full",
+            FontSize::Double => "double",
+            FontSize::Triple => "triple",
+        }
+    }
+
+    /// Returns the size for one of the original numbered scales 1-3.
+    pub fn from_number(scale: u8) -> Option<Self> {
+        match scale {
+            1 => Some(FontSize::Quadrant),
+            2 => Some(FontSize::HalfHeight),
+            3 => Some(FontSize::Full),
+            _ => None,
+        }
+    }
+
+    fn position(self) -> usize {
+        Self::ALL.iter().position(|&size| size == self).unwrap_or(0)
+    }
+
+    /// Returns the next smaller size, if any.
+    pub fn smaller(self) -> Option<Self> {
+        self.position()
+            .checked_sub(1)
+            .map(|position| Self::ALL[position])
+    }
+
+    /// Returns the next larger size, if any.
+    pub fn larger(self) -> Option<Self> {
+        Self::ALL.get(self.position() + 1).copied()
+    }
+}
+
+impl fmt::Display for FontSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for FontSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        s.parse::<u8>()
+            .ok()
+            .and_then(FontSize::from_number)
+            .or_else(|| {
+                Self::ALL
+                    .into_iter()
+                    .find(|size| size.name().eq_ignore_ascii_case(s))
+            })
+            .ok_or_else(|| {
+                format!(
+                    "unknown font size '{}' (expected 1-3 or one of: {})",
+                    s,
+                    size_names()
+                )
+            })
+    }
+}
+
+fn size_names() -> String {
+    let names: Vec<_> = FontSize::ALL.iter().map(|size| size.name()).collect();
+    names.join(", ")
+}
+
+/// The configured font size: a fixed size, or `auto` to pick the largest size
+/// that fits enough text on a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontScale {
+    Fixed(FontSize),
+    Auto,
+}
+
+impl Default for FontScale {
+    fn default() -> Self {
+        FontScale::Fixed(FontSize::default())
+    }
+}
+
+impl FromStr for FontScale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case("auto") {
+            Ok(FontScale::Auto)
+        } else {
+            s.parse().map(FontScale::Fixed).map_err(|_| {
+                format!(
+                    "unknown font size '{}' (expected auto, 1-3 or one of: {})",
+                    s.trim(),
+                    size_names()
+                )
+            })
+        }
+    }
+}
+
+impl ValueEnum for FontScale {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            FontScale::Fixed(FontSize::Sextant),
+            FontScale::Fixed(FontSize::Quadrant),
+            FontScale::Fixed(FontSize::ThirdHeight),
+            FontScale::Fixed(FontSize::HalfHeight),
+            FontScale::Fixed(FontSize::HalfWidth),
+            FontScale::Fixed(FontSize::Full),
+            FontScale::Fixed(FontSize::Double),
+            FontScale::Fixed(FontSize::Triple),
+            FontScale::Auto,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        let size = match self {
+            FontScale::Fixed(size) => *size,
+            FontScale::Auto => {
+                return Some(PossibleValue::new("auto").help(
+                    "The largest size that fits `min_line_chars` / `min_line_words` from the config",
+                ));
+            }
+        };
+        let (columns, rows) = match size {
+            FontSize::Sextant => (4, 3),
+            FontSize::Quadrant => (4, 4),
+            FontSize::ThirdHeight => (8, 3),
+            FontSize::HalfHeight => (8, 4),
+            FontSize::HalfWidth => (4, 8),
+            FontSize::Full => (8, 8),
+            FontSize::Double => (16, 16),
+            FontSize::Triple => (24, 24),
+        };
+        let value = PossibleValue::new(size.name())
+            .help(format!("{}x{} cells per character", columns, rows));
+        Some(match size {
+            FontSize::Quadrant => value.alias("1"),
+            FontSize::HalfHeight => value.alias("2"),
+            FontSize::Full => value.alias("3"),
+            _ => value,
+        })
+    }
+}
+
+impl Serialize for FontScale {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            FontScale::Fixed(size) => serializer.serialize_str(size.name()),
+            FontScale::Auto => serializer.serialize_str("auto"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FontScale {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// Older configs give the size as a number.
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Number(i64),
+            Name(String),
+        }
+
+        let text = match Repr::deserialize(deserializer)? {
+            Repr::Number(n) => n.to_string(),
+            Repr::Name(name) => name,
+        };
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Output format of a recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// asciicast v2, playable with `asciinema play`
+    Cast,
+    /// Animated GIF
+    Gif,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_line_names_match_the_config_file() {
+        for scale in FontScale::value_variants() {
+            let value = scale.to_possible_value().unwrap();
+            assert_eq!(value.get_name().parse::<FontScale>(), Ok(*scale));
+            for alias in value.get_name_and_aliases() {
+                assert_eq!(<FontScale as ValueEnum>::from_str(alias, false), Ok(*scale));
+            }
+        }
+        for others in OtherLines::value_variants() {
+            let name = others.to_possible_value().unwrap().get_name().to_string();
+            assert_eq!(
+                toml::Value::try_from(others).unwrap().as_str(),
+                Some(&*name)
+            );
+        }
+    }
+}
@@ -0,0 +1,336 @@
+//! Playlists: shows made of several scripts, prompted one after another.
+//!
+//! A playlist is a list of script files given on the command line, an M3U
+//! file with one script per line, or a TOML file with a `[[segment]]` table
+//! per script (TOML files without one are scripts):
+//!
+//! ```toml
+//! [[segment]]
+//! file = "intro.txt"
+//! title = "Welcome"
+//! speed = 3.0
+//!
+//! [[segment]]
+//! file = "keynote.md"
+//! duration = "4m30s"
+//! [segment.display]
+//! font_scale = "full"
+//! ```
+//!
+//! Besides `file` and `speed`, a segment takes the same keys as front matter.
+//! Paths are relative to the playlist file.
+
+use crate::config::{DisplayConfig, ScriptConfig, ScrollConfig};
+use crate::input::{DurationValue, FrontMatter};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// One script of a playlist.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Segment {
+    /// The script file, or `None` to read stdin or open the editor
+    pub file: Option<PathBuf>,
+    /// The title, duration and settings the playlist gives this script, which
+    /// override its own front matter
+    pub overrides: FrontMatter,
+}
+
+/// The scripts of a show, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Playlist {
+    /// Never empty
+    pub segments: Vec<Segment>,
+    /// Whether the scripts are listed in a playlist file, so even a single
+    /// one is read from its file rather than stdin
+    pub from_file: bool,
+}
+
+/// The keys a TOML playlist may have, for checking it before its settings are
+/// merged into the config.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlaylistKeys {
+    segment: Vec<SegmentKeys>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(dead_code)] // partly only parsed to report mistakes
+struct SegmentKeys {
+    file: PathBuf,
+    title: Option<String>,
+    speaker: Option<String>,
+    duration: Option<DurationValue>,
+    speed: Option<f64>,
+    display: Option<DisplayConfig>,
+    scroll: Option<ScrollConfig>,
+    script: Option<ScriptConfig>,
+    speakers: Option<BTreeMap<String, String>>,
+}
+
+impl Playlist {
+    /// Builds the playlist for the files given on the command line.
+    ///
+    /// A single `.m3u` or `.m3u8` file, or `.toml` file with `[[segment]]`
+    /// tables, is read as a playlist; other files are prompted in the order
+    /// given, and no files at all make one segment read from stdin or the
+    /// editor.
+    pub fn from_args(files: &[PathBuf]) -> Result<Self> {
+        let playlist = match files {
+            [] => Self::single(None),
+            [file] if is_playlist(file) => Self::from_file(file)?,
+            [file] => Self::single(Some(file.clone())),
+            files => Self {
+                segments: files
+                    .iter()
+                    .map(|file| Segment {
+                        file: Some(file.clone()),
+                        overrides: FrontMatter::default(),
+                    })
+                    .collect(),
+                from_file: false,
+            },
+        };
+        if playlist.segments.len() > 1 {
+            for file in playlist.segments.iter().filter_map(|s| s.file.as_ref()) {
+                if !file.is_file() {
+                    anyhow::bail!("Script {} not found", file.display());
+                }
+            }
+        }
+        Ok(playlist)
+    }
+
+    /// A playlist of one script.
+    pub fn single(file: Option<PathBuf>) -> Self {
+        Self {
+            segments: vec![Segment {
+                file,
+                overrides: FrontMatter::default(),
+            }],
+            from_file: false,
+        }
+    }
+
+    /// Reads an M3U or TOML playlist file, depending on its extension.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read playlist {}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let playlist = if has_extension(path, &["toml"]) {
+            Self::parse_toml(&content, dir)
+        } else {
+            Self::parse_m3u(&content, dir)
+        };
+        playlist.with_context(|| format!("Invalid playlist {}", path.display()))
+    }
+
+    /// Parses an M3U playlist: one script path per line, with `#` comments.
+    /// An `#EXTINF:<seconds>,<title>` line gives the next script's duration
+    /// (unless negative, for unknown) and title.
+    pub fn parse_m3u(content: &str, dir: &Path) -> Result<Self> {
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+        let mut segments = Vec::new();
+        let mut info = FrontMatter::default();
+        for line in content.lines().map(str::trim) {
+            if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+                let (seconds, title) = extinf.split_once(',').unwrap_or((extinf, ""));
+                // Attributes may follow the duration, as in `#EXTINF:300 tvg-id="x",`
+                let seconds = seconds.split_whitespace().next().unwrap_or_default();
+                info.duration = seconds
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|seconds| *seconds > 0.0)
+                    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok());
+                info.title = Some(title.trim())
+                    .filter(|title| !title.is_empty())
+                    .map(str::to_string);
+            } else if !line.is_empty() && !line.starts_with('#') {
+                segments.push(Segment {
+                    file: Some(dir.join(line)),
+                    overrides: std::mem::take(&mut info),
+                });
+            }
+        }
+        if segments.is_empty() {
+            anyhow::bail!("no scripts listed");
+        }
+        Ok(Self {
+            segments,
+            from_file: true,
+        })
+    }
+
+    /// Parses a TOML playlist with a `[[segment]]` table per script.
+    pub fn parse_toml(content: &str, dir: &Path) -> Result<Self> {
+        let keys: PlaylistKeys = toml::from_str(content)?;
+        let mut table: toml::Table = content.parse()?;
+        let tables = match table.remove("segment") {
+            Some(toml::Value::Array(tables)) => tables,
+            _ => unreachable!("checked by PlaylistKeys"),
+        };
+        if keys.segment.is_empty() {
+            anyhow::bail!("no scripts listed");
+        }
+
+        let mut segments = Vec::new();
+        for (i, (keys, table)) in keys.segment.into_iter().zip(tables).enumerate() {
+            let toml::Value::Table(mut table) = table else {
+                unreachable!("checked by PlaylistKeys")
+            };
+            table.remove("file");
+            // `speed` is short for `scroll.speed`
+            if let Some(speed) = table.remove("speed") {
+                let scroll = table
+                    .entry("scroll")
+                    .or_insert_with(|| toml::Table::new().into());
+                if let Some(scroll) = scroll.as_table_mut() {
+                    scroll.insert("speed".to_string(), speed);
+                }
+            }
+            let overrides = FrontMatter::from_parts(keys.title, keys.duration, table)
+                .with_context(|| format!("segment {}", i + 1))?;
+            segments.push(Segment {
+                file: Some(dir.join(keys.file)),
+                overrides,
+            });
+        }
+        Ok(Self {
+            segments,
+            from_file: true,
+        })
+    }
+}
+
+/// Returns whether a file given on the command line is a playlist rather than
+/// a script. Any other TOML file is a script like the rest.
+fn is_playlist(path: &Path) -> bool {
+    if has_extension(path, &["m3u", "m3u8"]) {
+        return true;
+    }
+    has_extension(path, &["toml"])
+        && std::fs::read_to_string(path).is_ok_and(|content| {
+            content
+                .parse::<toml::Table>()
+                .is_ok_and(|table| table.contains_key("segment"))
+        })
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn m3u_playlists_list_scripts_with_optional_titles() {
+        let content = "#EXTM3U\n#EXTINF:90,Welcome\nintro.txt\n\n# not a script\n\
+                       #EXTINF:-1,\n/talks/keynote.md\noutro.txt\n";
+        let playlist = Playlist::parse_m3u(content, Path::new("show")).unwrap();
+        let files: Vec<_> = playlist
+            .segments
+            .iter()
+            .map(|s| s.file.clone().unwrap())
+            .collect();
+        assert_eq!(
+            files,
+            [
+                PathBuf::from("show/intro.txt"),
+                PathBuf::from("/talks/keynote.md"),
+                PathBuf::from("show/outro.txt")
+            ]
+        );
+        let first = &playlist.segments[0].overrides;
+        assert_eq!(first.title.as_deref(), Some("Welcome"));
+        assert_eq!(first.duration, Some(Duration::from_secs(90)));
+        assert_eq!(playlist.segments[1].overrides, FrontMatter::default());
+        assert_eq!(playlist.segments[2].overrides, FrontMatter::default());
+
+        assert!(Playlist::parse_m3u("#EXTM3U\n", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn toml_files_without_segments_are_scripts() {
+        let dir = std::env::temp_dir().join(format!("tp-playlist-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let notes = dir.join("notes.toml");
+        let show = dir.join("show.toml");
+        std::fs::write(&notes, "title = \"Not a playlist\"\n").unwrap();
+        std::fs::write(&show, "[[segment]]\nfile = \"notes.toml\"\n").unwrap();
+
+        let as_script = Playlist::from_args(std::slice::from_ref(&notes));
+        let as_playlist = Playlist::from_args(std::slice::from_ref(&show));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(as_script.unwrap(), Playlist::single(Some(notes.clone())));
+        assert_eq!(as_playlist.unwrap().segments[0].file, Some(notes));
+    }
+
+    #[test]
+    fn playlist_files_of_one_script_are_read_from_the_file() {
+        let dir = std::env::temp_dir().join(format!("tp-playlist-one-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let m3u = dir.join("show.m3u");
+        let toml = dir.join("show.toml");
+        std::fs::write(&m3u, "#EXTM3U\nintro.txt\n").unwrap();
+        std::fs::write(&toml, "[[segment]]\nfile = \"intro.txt\"\n").unwrap();
+
+        let playlists = [m3u, toml].map(|file| Playlist::from_args(&[file]));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        for playlist in playlists {
+            let playlist = playlist.unwrap();
+            assert!(playlist.from_file);
+            assert_eq!(playlist.segments.len(), 1);
+            assert_eq!(playlist.segments[0].file, Some(dir.join("intro.txt")));
+        }
+        assert!(
+            !Playlist::from_args(&[dir.join("intro.txt")])
+                .unwrap()
+                .from_file
+        );
+    }
+
+    #[test]
+    fn toml_playlists_give_each_segment_settings() {
+        let content = r#"
+[[segment]]
+file = "intro.txt"
+title = "Welcome"
+speed = 3.0
+speaker = "ALICE"
+
+[[segment]]
+file = "keynote.md"
+duration = "4m30s"
+[segment.display]
+font_scale = "full"
+"#;
+        let playlist = Playlist::parse_toml(content, Path::new("")).unwrap();
+        let [intro, keynote] = &playlist.segments[..] else {
+            panic!("expected two segments");
+        };
+        assert_eq!(intro.file, Some(PathBuf::from("intro.txt")));
+        assert_eq!(intro.overrides.title.as_deref(), Some("Welcome"));
+        assert_eq!(
+            intro.overrides.settings.to_string(),
+            "[scroll]\nspeed = 3.0\n\n[script]\nspeaker = \"ALICE\"\n"
+        );
+        assert_eq!(keynote.overrides.duration, Some(Duration::from_secs(270)));
+        assert_eq!(
+            keynote.overrides.settings.to_string(),
+            "[display]\nfont_scale = \"full\"\n"
+        );
+
+        let error = Playlist::parse_toml("[[segment]]\nfile = \"a\"\nsped = 2\n", Path::new(""))
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("sped"), "{error:#}");
+    }
+}
@@ -0,0 +1,88 @@
+//! Cache of rasterized big-text lines.
+//!
+//! Drawing a line with `BigText` looks up and rasterizes every font8x8 glyph
+//! again. Wrapped lines rarely change while the text scrolls, so each line is
+//! rendered once into its own buffer and copied into the frame from then on.
+
+use crate::app::ScriptLine;
+use crate::config::{Align, FontSize};
+use ratatui::{buffer::Buffer, layout::Rect, style::Color};
+use std::collections::HashMap;
+
+/// Number of cached lines above which lines not drawn in the current frame are
+/// dropped. Far more than fit on any screen, so scrolling back a little is
+/// still cached.
+const CAPACITY: usize = 512;
+
+/// Everything that affects how a line is rasterized.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct LineKey {
+    line: ScriptLine,
+    scale: FontSize,
+    align: Align,
+    width: u16,
+    text_color: Color,
+    background_color: Color,
+}
+
+/// Rasterized lines keyed by text, scale, alignment and style.
+#[derive(Debug, Default)]
+pub struct LineCache {
+    lines: HashMap<LineKey, (Buffer, u64)>,
+    frame: u64,
+}
+
+impl LineCache {
+    /// Returns the cached raster of `line`, calling `rasterize` to draw it into
+    /// a `width` × `height` buffer at the origin if it isn't cached yet.
+    pub fn get_or_insert(
+        &mut self,
+        line: &ScriptLine,
+        (scale, align): (FontSize, Align),
+        (width, height): (u16, u16),
+        (text_color, background_color): (Color, Color),
+        rasterize: impl FnOnce(&mut Buffer),
+    ) -> &Buffer {
+        let key = LineKey {
+            line: line.clone(),
+            scale,
+            align,
+            width,
+            text_color,
+            background_color,
+        };
+        let frame = self.frame;
+        let (buffer, last_used) = self.lines.entry(key).or_insert_with(|| {
+            let mut buffer = Buffer::empty(Rect::new(0, 0, width, height));
+            rasterize(&mut buffer);
+            (buffer, frame)
+        });
+        *last_used = frame;
+        buffer
+    }
+
+    /// Marks the end of a frame, evicting lines that weren't drawn in it once
+    /// the cache has grown past its capacity.
+    pub fn end_frame(&mut self) {
+        if self.lines.len() > CAPACITY {
+            let frame = self.frame;
+            self.lines.retain(|_, (_, last_used)| *last_used == frame);
+        }
+        self.frame += 1;
+    }
+
+    /// Drops every cached line.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// Returns the number of cached lines.
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Returns whether no lines are cached.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
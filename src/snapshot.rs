@@ -0,0 +1,155 @@
+//! Headless rendering of the teleprompter to text or ANSI snapshots.
+
+use crate::app::App;
+use crate::ui;
+use ratatui::{
+    Terminal,
+    backend::TestBackend,
+    buffer::{Buffer, Cell},
+    layout::Rect,
+    style::{Color, Modifier, Style},
+};
+use std::fmt::Write;
+use unicode_width::UnicodeWidthStr;
+
+/// Renders the complete UI for `app` at its current scroll offset into a
+/// buffer of the given size, without touching the terminal.
+pub fn render_to_buffer(app: &mut App, width: u16, height: u16) -> Buffer {
+    let mut terminal =
+        Terminal::new(TestBackend::new(width, height)).expect("test backend never fails");
+    terminal
+        .draw(|frame| ui::render(frame, app))
+        .expect("test backend never fails");
+    terminal.backend().buffer().clone()
+}
+
+/// Wraps the app's text for the given width and renders every line one after
+/// another, without scrolling or the status bar. This is `tp --print`.
+///
+/// Yields one buffer per wrapped line, so arbitrarily long scripts can be
+/// printed without building one huge buffer.
+pub fn render_banner(app: &mut App, width: u16) -> impl Iterator<Item = Buffer> + '_ {
+    if let Some(min_chars) = app.auto_fit {
+        let content_width =
+            ui::padded_area(Rect::new(0, 0, width, 1), app.horizontal_padding).width;
+        app.font_scale = ui::auto_scale(min_chars, content_width, u16::MAX, 0);
+    }
+    app.line_height = ui::get_line_height(app.font_scale);
+    let line_area = Rect::new(0, 0, width, app.line_height);
+    let content_area = ui::padded_area(line_area, app.horizontal_padding);
+    app.update_wrap(ui::max_chars(app.font_scale, content_area.width));
+
+    let mut idx = 0;
+    std::iter::from_fn(move || {
+        let align = app.line_align(idx);
+        let line = app.wrapped_line(idx)?.clone();
+        // Each buffer ends with the spacing below its line
+        let rows = app.line_row(idx + 1) - app.line_row(idx);
+        idx += 1;
+
+        let full_area = Rect {
+            height: rows as u16,
+            ..line_area
+        };
+        let mut buf = Buffer::empty(full_area);
+        buf.set_style(full_area, Style::default().bg(app.background_color));
+        ui::render_line(&mut buf, app, &line, (app.font_scale, align), content_area);
+        Some(buf)
+    })
+}
+
+/// Returns the symbols in the buffer as plain text, one line per row, with
+/// trailing spaces removed.
+pub fn buffer_to_text(buf: &Buffer) -> String {
+    let mut text = String::new();
+    for row in rows(buf) {
+        let line: String = visible_cells(row).map(Cell::symbol).collect();
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+/// Returns the buffer as text with ANSI SGR escapes for colors and modifiers.
+///
+/// Every row starts from the default style and ends with a reset, so rows can
+/// be printed or diffed independently.
+pub fn buffer_to_ansi(buf: &Buffer) -> String {
+    let mut text = String::new();
+    for row in rows(buf) {
+        let mut current: Option<(Color, Color, Modifier)> = None;
+        for cell in visible_cells(row) {
+            let style = (cell.fg, cell.bg, cell.modifier);
+            if current != Some(style) {
+                text.push_str("\x1b[0");
+                push_sgr_color(&mut text, cell.fg, false);
+                push_sgr_color(&mut text, cell.bg, true);
+                push_sgr_modifiers(&mut text, cell.modifier);
+                text.push('m');
+                current = Some(style);
+            }
+            text.push_str(cell.symbol());
+        }
+        text.push_str("\x1b[0m\n");
+    }
+    text
+}
+
+fn rows(buf: &Buffer) -> impl Iterator<Item = &[Cell]> {
+    buf.content.chunks(buf.area.width.max(1) as usize)
+}
+
+/// Skips the cells hidden behind multi-width symbols.
+fn visible_cells(row: &[Cell]) -> impl Iterator<Item = &Cell> {
+    let mut hidden = 0;
+    row.iter().filter(move |cell| {
+        if hidden > 0 {
+            hidden -= 1;
+            return false;
+        }
+        hidden = cell.symbol().width().saturating_sub(1);
+        true
+    })
+}
+
+fn push_sgr_color(text: &mut String, color: Color, background: bool) {
+    let base = if background { 40 } else { 30 };
+    let _ = match color {
+        Color::Reset => Ok(()),
+        Color::Black => write!(text, ";{}", base),
+        Color::Red => write!(text, ";{}", base + 1),
+        Color::Green => write!(text, ";{}", base + 2),
+        Color::Yellow => write!(text, ";{}", base + 3),
+        Color::Blue => write!(text, ";{}", base + 4),
+        Color::Magenta => write!(text, ";{}", base + 5),
+        Color::Cyan => write!(text, ";{}", base + 6),
+        Color::Gray => write!(text, ";{}", base + 7),
+        Color::DarkGray => write!(text, ";{}", base + 60),
+        Color::LightRed => write!(text, ";{}", base + 61),
+        Color::LightGreen => write!(text, ";{}", base + 62),
+        Color::LightYellow => write!(text, ";{}", base + 63),
+        Color::LightBlue => write!(text, ";{}", base + 64),
+        Color::LightMagenta => write!(text, ";{}", base + 65),
+        Color::LightCyan => write!(text, ";{}", base + 66),
+        Color::White => write!(text, ";{}", base + 67),
+        Color::Indexed(i) => write!(text, ";{};5;{}", base + 8, i),
+        Color::Rgb(r, g, b) => write!(text, ";{};2;{};{};{}", base + 8, r, g, b),
+    };
+}
+
+fn push_sgr_modifiers(text: &mut String, modifier: Modifier) {
+    const CODES: [(Modifier, &str); 7] = [
+        (Modifier::BOLD, ";1"),
+        (Modifier::DIM, ";2"),
+        (Modifier::ITALIC, ";3"),
+        (Modifier::UNDERLINED, ";4"),
+        (Modifier::SLOW_BLINK, ";5"),
+        (Modifier::REVERSED, ";7"),
+        (Modifier::CROSSED_OUT, ";9"),
+    ];
+    for (flag, code) in CODES {
+        if modifier.contains(flag) {
+            text.push_str(code);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Terminal setup and restoration that survives panics and signals.
+
+use anyhow::Result;
+use crossterm::{
+    cursor::Show,
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use signal_hook::consts::{SIGCONT, SIGHUP, SIGINT, SIGTERM, SIGTSTP};
+use std::fs::File;
+use std::io;
+use std::panic;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Puts the terminal into raw mode (and the alternate screen, unless inline)
+/// and restores it when dropped, including while unwinding from a panic.
+pub struct TerminalGuard {
+    inline: bool,
+    active: bool,
+}
+
+impl TerminalGuard {
+    pub fn enter(inline: bool) -> Result<Self> {
+        let mut guard = Self {
+            inline,
+            active: false,
+        };
+        guard.resume()?;
+        Ok(guard)
+    }
+
+    /// Restores the terminal, e.g. before the process is stopped.
+    pub fn suspend(&mut self) {
+        if self.active {
+            restore(self.inline);
+            self.active = false;
+        }
+    }
+
+    /// Sets the terminal up again after `suspend`.
+    pub fn resume(&mut self) -> Result<()> {
+        enable_raw_mode()?;
+        if !self.inline {
+            execute!(tty()?, EnterAlternateScreen)?;
+        }
+        self.active = true;
+        Ok(())
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        self.suspend();
+    }
+}
+
+/// Restores the terminal before the default panic message is printed, so the
+/// message is readable and the shell is usable afterwards.
+pub fn install_panic_hook(inline: bool) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore(inline);
+        default_hook(info);
+    }));
+}
+
+/// Best-effort restoration; errors are ignored because this also runs while
+/// panicking, where there is nothing better to do.
+fn restore(inline: bool) {
+    let _ = disable_raw_mode();
+    if let Ok(mut tty) = tty() {
+        if !inline {
+            let _ = execute!(tty, LeaveAlternateScreen);
+        }
+        let _ = execute!(tty, Show);
+    }
+}
+
+fn tty() -> io::Result<File> {
+    File::options().write(true).open("/dev/tty")
+}
+
+/// Flags set from signal handlers and polled by the main loop.
+pub struct Signals {
+    quit: Arc<AtomicBool>,
+    suspend: Arc<AtomicBool>,
+    resumed: Arc<AtomicBool>,
+}
+
+impl Signals {
+    /// Registers handlers for SIGTERM, SIGHUP and SIGINT (quit), SIGTSTP
+    /// (suspend) and SIGCONT (redraw after being continued). Call this before
+    /// [`TerminalGuard::enter`] so no signal can leave the terminal raw.
+    pub fn register() -> Result<Self> {
+        let signals = Self {
+            quit: Arc::new(AtomicBool::new(false)),
+            suspend: Arc::new(AtomicBool::new(false)),
+            resumed: Arc::new(AtomicBool::new(false)),
+        };
+        for signal in [SIGTERM, SIGHUP, SIGINT] {
+            signal_hook::flag::register(signal, Arc::clone(&signals.quit))?;
+        }
+        signal_hook::flag::register(SIGTSTP, Arc::clone(&signals.suspend))?;
+        signal_hook::flag::register(SIGCONT, Arc::clone(&signals.resumed))?;
+        Ok(signals)
+    }
+
+    pub fn quit_requested(&self) -> bool {
+        self.quit.load(Ordering::Relaxed)
+    }
+
+    /// Returns true once per SIGTSTP received.
+    pub fn take_suspend(&self) -> bool {
+        self.suspend.swap(false, Ordering::Relaxed)
+    }
+
+    /// Returns true once per SIGCONT received.
+    pub fn take_resumed(&self) -> bool {
+        self.resumed.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// Restores the terminal and stops the process like an unhandled SIGTSTP
+/// would. Returns once the process is continued, with the terminal set up again.
+pub fn suspend_process(guard: &mut TerminalGuard) -> Result<()> {
+    guard.suspend();
+    signal_hook::low_level::emulate_default_handler(SIGTSTP)?;
+    guard.resume()
+}
@@ -0,0 +1,248 @@
+//! Color themes: built-in palettes and theme files.
+//!
+//! A theme is a small TOML file naming a color for each part of the prompter:
+//!
+//! ```toml
+//! text = "#FFB000"
+//! background = "black"
+//! highlight = "#FFD580"   # section markers
+//! guide = "#805800"       # the rules either side of a section marker
+//! status_bar = "#FFB000"
+//! dimmed = "#996A00"      # other characters' or speakers' lines
+//!
+//! [speakers]
+//! ALICE = "lightcyan"
+//! ```
+//!
+//! Every key is optional. Colors set directly in the config file or on the
+//! command line take precedence over the theme's.
+
+use crate::color::parse_color;
+use crate::config::Config;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Themes that ship with tp, by name.
+const BUILTIN: [(&str, &str); 4] = [
+    ("classic", include_str!("../themes/classic.toml")),
+    (
+        "high-contrast",
+        include_str!("../themes/high-contrast.toml"),
+    ),
+    ("amber", include_str!("../themes/amber.toml")),
+    ("solarized", include_str!("../themes/solarized.toml")),
+];
+
+/// A set of colors for the prompter, as color names or hex codes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Prompt text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+    /// Section marker labels, such as scene headings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<String>,
+    /// The rules drawn either side of a section marker
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guide: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_bar: Option<String>,
+    /// Lines shown dimmed for context
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimmed: Option<String>,
+    /// Text color per speaker name in `NAME: line` scripts
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub speakers: BTreeMap<String, String>,
+}
+
+impl Theme {
+    /// Returns the names of the built-in themes.
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN.iter().map(|(name, _)| *name)
+    }
+
+    /// Returns the built-in theme with the given name, if there is one.
+    pub fn builtin(name: &str) -> Option<Self> {
+        BUILTIN
+            .iter()
+            .find(|(builtin, _)| builtin.eq_ignore_ascii_case(name.trim()))
+            .map(|(_, source)| toml::from_str(source).expect("built-in themes are valid"))
+    }
+
+    /// Loads a theme by name or path.
+    ///
+    /// Built-in names come first, then `<name>.toml` in the `themes` directory
+    /// next to the config file, and finally `name` as a path to a theme file.
+    pub fn load(name: &str) -> Result<Self> {
+        Self::load_from(name, Self::themes_dir().as_deref())
+    }
+
+    fn load_from(name: &str, themes_dir: Option<&Path>) -> Result<Self> {
+        let Some(path) = Self::file_path_in(name, themes_dir) else {
+            return Ok(Self::builtin(name).expect("only built-in themes have no file"));
+        };
+        if !path.is_file() {
+            let names: Vec<_> = Self::builtin_names().collect();
+            let searched = match themes_dir {
+                Some(dir) => format!(", {} in {}", file_name(name), dir.display()),
+                None => String::new(),
+            };
+            anyhow::bail!(
+                "Unknown theme '{}': not a built-in theme ({}){}, or a path to a theme file",
+                name,
+                names.join(", "),
+                searched
+            );
+        }
+        Self::from_file(&path)
+    }
+
+    /// Returns the file that [`Theme::load`] reads for `name`, or `None` for a
+    /// built-in theme.
+    pub fn file_path(name: &str) -> Option<PathBuf> {
+        Self::file_path_in(name, Self::themes_dir().as_deref())
+    }
+
+    fn file_path_in(name: &str, themes_dir: Option<&Path>) -> Option<PathBuf> {
+        if Self::builtin(name).is_some() {
+            return None;
+        }
+        let path = themes_dir
+            .map(|dir| dir.join(file_name(name)))
+            .filter(|path| path.is_file())
+            .unwrap_or_else(|| PathBuf::from(name));
+        Some(path)
+    }
+
+    /// Reads a theme file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read theme {}", path.display()))?;
+        let theme: Theme = toml::from_str(&content)
+            .with_context(|| format!("Invalid theme {}", path.display()))?;
+        theme
+            .check_colors()
+            .with_context(|| format!("Invalid theme {}", path.display()))?;
+        Ok(theme)
+    }
+
+    /// Checks that every color in the theme parses, naming the first one that
+    /// doesn't.
+    pub fn check_colors(&self) -> Result<()> {
+        let keys = [
+            ("text", &self.text),
+            ("background", &self.background),
+            ("highlight", &self.highlight),
+            ("guide", &self.guide),
+            ("status_bar", &self.status_bar),
+            ("dimmed", &self.dimmed),
+        ];
+        for (key, color) in keys {
+            if let Some(color) = color {
+                parse_color(color).map_err(|e| anyhow::anyhow!("{}: {}", key, e))?;
+            }
+        }
+        for (name, color) in &self.speakers {
+            parse_color(color).map_err(|e| anyhow::anyhow!("speakers.{}: {}", name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the directory user themes are looked up in
+    /// (~/.config/tui_prompter/themes).
+    fn themes_dir() -> Option<PathBuf> {
+        let config_path = Config::config_path().ok()?;
+        Some(config_path.parent()?.join("themes"))
+    }
+}
+
+/// Returns the file a theme name stands for in the themes directory. The
+/// extension is appended rather than set, so `studio.dark` is
+/// `studio.dark.toml`; a name that already ends in it is used as is.
+fn file_name(name: &str) -> String {
+    if name.ends_with(".toml") {
+        name.to_string()
+    } else {
+        format!("{name}.toml")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_themes_set_every_color() {
+        for name in Theme::builtin_names() {
+            let theme = Theme::builtin(name).unwrap();
+            theme.check_colors().unwrap();
+            for color in [
+                &theme.text,
+                &theme.background,
+                &theme.highlight,
+                &theme.guide,
+                &theme.status_bar,
+                &theme.dimmed,
+            ] {
+                assert!(color.is_some(), "{name} leaves a color unset");
+            }
+        }
+    }
+
+    #[test]
+    fn config_colors_override_the_theme() {
+        let mut config = Config::default();
+        config.display.theme = Some("amber".to_string());
+        config.display.text_color = Some("white".to_string());
+        config
+            .speakers
+            .insert("alice".to_string(), "red".to_string());
+        let mut theme = Theme::builtin("amber").unwrap();
+        theme
+            .speakers
+            .insert("ALICE".to_string(), "blue".to_string());
+        theme
+            .speakers
+            .insert("BOB".to_string(), "green".to_string());
+
+        config.apply_theme(theme);
+        assert_eq!(config.display.text_color.as_deref(), Some("white"));
+        assert_eq!(config.display.background_color.as_deref(), Some("black"));
+        assert_eq!(config.display.dimmed_color.as_deref(), Some("#996A00"));
+        assert_eq!(config.speakers.len(), 2);
+        assert_eq!(config.speakers["alice"], "red");
+        assert_eq!(config.speakers["BOB"], "green");
+    }
+
+    #[test]
+    fn theme_names_are_looked_up_in_the_themes_directory() {
+        let dir = std::env::temp_dir().join(format!("tp-themes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("studio.dark.toml"), "text = \"green\"\n").unwrap();
+        std::fs::write(dir.join("night.toml"), "text = \"blue\"\n").unwrap();
+
+        let dotted = Theme::load_from("studio.dark", Some(&dir));
+        let with_extension = Theme::load_from("night.toml", Some(&dir));
+        let builtin = Theme::file_path_in("amber", Some(&dir));
+        let missing = Theme::load_from("studio.light", Some(&dir));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(dotted.unwrap().text.as_deref(), Some("green"));
+        assert_eq!(with_extension.unwrap().text.as_deref(), Some("blue"));
+        assert_eq!(builtin, None);
+        let error = missing.unwrap_err().to_string();
+        assert!(error.contains("Unknown theme 'studio.light'"), "{error}");
+        assert!(error.contains(&dir.display().to_string()), "{error}");
+    }
+
+    #[test]
+    fn unknown_theme_keys_are_rejected() {
+        let error = toml::from_str::<Theme>("text = \"red\"\ndim = \"gray\"\n").unwrap_err();
+        assert!(error.to_string().contains("unknown field `dim`"), "{error}");
+    }
+}
@@ -1,111 +1,434 @@
 //! Terminal UI rendering using ratatui and tui-big-text.
 
-use crate::app::App;
+use crate::app::{App, LineKind, ScriptLine, WrapKey};
+use crate::config::{Align, FontSize};
 use ratatui::{
     Frame,
+    buffer::Buffer,
     layout::{Alignment, Rect},
     style::{Modifier, Style},
-    widgets::{Block, Paragraph},
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph, StatefulWidget, Widget, Wrap},
 };
+use std::time::Duration;
 use tui_big_text::{BigText, PixelSize};
+use unicode_width::UnicodeWidthStr;
 
 /// Renders the complete teleprompter UI.
 pub fn render(frame: &mut Frame, app: &mut App) {
     let area = frame.area();
 
-    let main_block = Block::default().style(Style::default().bg(app.background_color));
-    frame.render_widget(main_block, area);
+    let text_area = Rect {
+        height: area.height.saturating_sub(1),
+        ..area
+    };
+
+    frame.render_stateful_widget(Teleprompter, text_area, app);
+    render_status_bar(frame.buffer_mut(), app, area);
+    if let Some((message, _)) = &app.overlay {
+        render_overlay(frame.buffer_mut(), app, message, text_area);
+    }
+}
+
+/// A widget that draws the scrolling big text of an [`App`].
+///
+/// The area is filled with the app's background color and padded by its
+/// `horizontal_padding`. Text is rewrapped whenever the area width changes, and
+/// `App::visible_height` is updated to match the area. The status bar is not
+/// part of the widget.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Teleprompter;
+
+impl StatefulWidget for Teleprompter {
+    type State = App;
+
+    fn render(self, area: Rect, buf: &mut Buffer, app: &mut App) {
+        Block::default()
+            .style(Style::default().bg(app.background_color))
+            .render(area, buf);
+
+        render_teleprompter(buf, app, area);
+    }
+}
 
-    let horizontal_pad = (area.width as u32 * app.horizontal_padding as u32 / 100) as u16;
-    let content_area = Rect {
+/// Shrinks an area horizontally by `padding` percent of its width on each side.
+pub(crate) fn padded_area(area: Rect, padding: u16) -> Rect {
+    let horizontal_pad = (area.width as u32 * padding as u32 / 100) as u16;
+    Rect {
         x: area.x + horizontal_pad,
-        y: area.y,
         width: area.width.saturating_sub(horizontal_pad * 2),
-        height: area.height.saturating_sub(1),
-    };
+        ..area
+    }
+}
 
-    render_teleprompter(frame, app, content_area);
-    render_status_bar(frame, app, area);
+/// Returns how many big-text characters fit on a line of the given width.
+pub(crate) fn max_chars(scale: FontSize, width: u16) -> usize {
+    ((width / get_char_width(scale)) as usize)
+        .saturating_sub(1)
+        .max(1)
 }
 
-fn get_pixel_size(scale: u8) -> PixelSize {
+/// Returns the `PixelSize` a font size is drawn with, and how many times it is
+/// scaled up afterwards.
+fn get_pixel_size(scale: FontSize) -> (PixelSize, u16) {
     match scale {
-        1 => PixelSize::Quadrant,
-        2 => PixelSize::HalfHeight,
-        _ => PixelSize::Full,
+        FontSize::Sextant => (PixelSize::Sextant, 1),
+        FontSize::Quadrant => (PixelSize::Quadrant, 1),
+        FontSize::ThirdHeight => (PixelSize::ThirdHeight, 1),
+        FontSize::HalfHeight => (PixelSize::HalfHeight, 1),
+        FontSize::HalfWidth => (PixelSize::HalfWidth, 1),
+        FontSize::Full => (PixelSize::Full, 1),
+        FontSize::Double => (PixelSize::Full, 2),
+        FontSize::Triple => (PixelSize::Full, 3),
     }
 }
 
 /// Returns character width in terminal columns for given scale (based on font8x8).
-fn get_char_width(scale: u8) -> u16 {
+fn get_char_width(scale: FontSize) -> u16 {
     match scale {
-        1 => 4,
-        _ => 8,
+        FontSize::Sextant | FontSize::Quadrant | FontSize::HalfWidth => 4,
+        FontSize::ThirdHeight | FontSize::HalfHeight | FontSize::Full => 8,
+        FontSize::Double => 16,
+        FontSize::Triple => 24,
     }
 }
 
 /// Returns line height in terminal rows for given scale (based on font8x8).
-fn get_line_height(scale: u8) -> u16 {
+pub(crate) fn get_line_height(scale: FontSize) -> u16 {
     match scale {
-        1 | 2 => 4,
-        _ => 8,
+        FontSize::Sextant | FontSize::ThirdHeight => 3,
+        FontSize::Quadrant | FontSize::HalfHeight => 4,
+        FontSize::HalfWidth | FontSize::Full => 8,
+        FontSize::Double => 16,
+        FontSize::Triple => 24,
     }
 }
 
-fn render_teleprompter(frame: &mut Frame, app: &mut App, area: Rect) {
-    let line_height = get_line_height(app.font_scale);
-    let char_width = get_char_width(app.font_scale);
-    let visible_lines = (area.height / line_height) as usize;
-    let max_chars = ((area.width / char_width) as usize)
-        .saturating_sub(1)
-        .max(1);
+/// Number of source lines not counted yet whose wrapped length is counted per
+/// frame, so the total of a long script becomes known without a stall.
+const INDEX_CHUNK: usize = 2000;
 
-    if app.last_width != area.width {
-        app.update_wrap(max_chars);
-        app.last_width = area.width;
+fn render_teleprompter(buf: &mut Buffer, app: &mut App, full_area: Rect) {
+    let area = padded_area(full_area, app.horizontal_padding);
+    let scale = match app.auto_fit {
+        Some(min_chars) => {
+            app.font_scale = auto_scale(min_chars, area.width, area.height, app.line_spacing);
+            app.font_scale
+        }
+        None => fit_scale(app.font_scale, area.height),
+    };
+    let line_height = get_line_height(scale);
+    let pitch = line_height + app.line_spacing;
+    // The last line doesn't need spacing below it
+    let visible_lines = (area.height.saturating_add(app.line_spacing) / pitch) as usize;
+
+    let key = WrapKey {
+        width: full_area.width,
+        scale,
+        padding: app.horizontal_padding,
+    };
+    let relaid = app.wrap_key != Some(key);
+    if relaid {
+        if app.wrap_key.is_some() {
+            app.relayout(max_chars(scale, area.width), visible_lines, line_height);
+        } else {
+            app.update_wrap(max_chars(scale, area.width));
+        }
+        app.wrap_key = Some(key);
     }
 
     app.visible_height = visible_lines;
+    app.line_height = line_height;
+    if relaid {
+        app.pace_to_target();
+    }
 
-    let total_wrapped_lines = app.wrapped_lines.len();
-    let pixel_size = get_pixel_size(app.font_scale);
-    let style = Style::default().fg(app.text_color).bg(app.background_color);
-    let scroll_line = app.scroll_offset as isize;
+    // Text moves up one row at a time; lines cut off by the top or bottom of the
+    // window are drawn partially
+    let visible_rows = visible_lines as i64 * pitch as i64;
+    let window = Rect {
+        height: visible_rows.min(area.height as i64) as u16,
+        ..area
+    };
+    let first_row = scroll_row(app) - visible_rows;
 
-    for i in 0..visible_lines {
-        let line_idx = scroll_line - (visible_lines as isize) + (i as isize);
+    // Rasterizing needs the app, so the cache is taken out while drawing
+    let mut cache = std::mem::take(&mut app.raster_cache);
+    let colors = (app.text_color, app.background_color);
 
-        let text = if line_idx >= 0 && (line_idx as usize) < total_wrapped_lines {
-            app.wrapped_lines[line_idx as usize].as_str()
-        } else {
-            ""
+    let mut line_idx = app.line_at_row(first_row.max(0) as u64);
+    loop {
+        let top = app.line_row(line_idx) as i64 - first_row;
+        if top >= window.height as i64 {
+            break;
+        }
+        let align = app.line_align(line_idx);
+        let Some(line) = app.wrapped_line(line_idx).cloned() else {
+            break;
         };
+        line_idx += 1;
+        if top + line_height as i64 <= 0 {
+            continue;
+        }
+
+        let raster = cache.get_or_insert(
+            &line,
+            (scale, align),
+            (window.width, line_height),
+            colors,
+            |raster| {
+                let slot = raster.area;
+                raster.set_style(slot, Style::default().bg(app.background_color));
+                render_line(raster, app, &line, (scale, align), slot);
+            },
+        );
+        blit_rows(buf, raster, window, top);
+    }
+
+    cache.end_frame();
+    app.raster_cache = cache;
 
-        if text.is_empty() {
+    // Count the rest of the script a chunk at a time, for the status bar
+    app.wrapped.index_more(&app.lines, INDEX_CHUNK);
+}
+
+/// Returns the scroll position in terminal rows at the scale and spacing of
+/// the last render.
+///
+/// The text only moves on screen when this changes, so hosts can skip redrawing
+/// until it does.
+pub fn scroll_row(app: &App) -> i64 {
+    (app.scroll_offset * app.row_pitch() as f64).floor() as i64
+}
+
+/// Returns how long until the scrolling text next moves by a row, or `None` if
+/// it is paused and won't move on its own.
+///
+/// Faster speeds, larger font scales and line spacing (more rows per line)
+/// move more often.
+pub fn next_frame(app: &App) -> Option<Duration> {
+    if app.paused || app.should_quit || app.speed <= 0.0 {
+        return None;
+    }
+    let pitch = app.row_pitch() as f64;
+    let rows_per_second = app.speed * pitch;
+    let rows = app.scroll_offset * pitch;
+    let until_next_row = (rows.floor() + 1.0 - rows) / rows_per_second;
+    Some(Duration::from_secs_f64(until_next_row.max(0.0)))
+}
+
+/// Copies a rasterized line into `window` with its first row at offset `top`
+/// (which may be negative), skipping rows outside the window.
+fn blit_rows(buf: &mut Buffer, raster: &Buffer, window: Rect, top: i64) {
+    for row in 0..raster.area.height {
+        let y = top + row as i64;
+        if !(0..window.height as i64).contains(&y) {
             continue;
         }
+        for col in 0..raster.area.width.min(window.width) {
+            buf[(window.x + col, window.y + y as u16)] = raster[(col, row)].clone();
+        }
+    }
+}
 
-        let line_area = Rect {
-            x: area.x,
-            y: area.y + (i as u16 * line_height),
-            width: area.width,
-            height: line_height,
-        };
+/// Returns the largest scale up to `scale` whose lines fit in `height` rows,
+/// e.g. for a short inline viewport.
+fn fit_scale(scale: FontSize, height: u16) -> FontSize {
+    FontSize::ALL
+        .into_iter()
+        .rev()
+        .filter(|&s| s <= scale)
+        .find(|&s| get_line_height(s) <= height)
+        .unwrap_or(FontSize::ALL[0])
+}
+
+/// Returns the largest scale that fits at least `min_chars` characters on a
+/// line of the given width and whose lines, with `line_spacing` blank rows
+/// below each, fit in `height` rows, or the smallest scale if none does.
+pub(crate) fn auto_scale(min_chars: usize, width: u16, height: u16, line_spacing: u16) -> FontSize {
+    FontSize::ALL
+        .into_iter()
+        .rev()
+        .find(|&s| max_chars(s, width) >= min_chars && get_line_height(s) + line_spacing <= height)
+        .unwrap_or(FontSize::ALL[0])
+}
+
+/// Draws one wrapped line at the given scale and alignment into its line
+/// slot, styled by its kind.
+pub(crate) fn render_line(
+    buf: &mut Buffer,
+    app: &App,
+    line: &ScriptLine,
+    (scale, align): (FontSize, Align),
+    area: Rect,
+) {
+    if line.text.is_empty() {
+        return;
+    }
+
+    let style = Style::default()
+        .fg(line.color.unwrap_or(app.text_color))
+        .bg(app.background_color);
+
+    let (scale, style) = match line.kind {
+        LineKind::Marker => {
+            render_marker(buf, app, &line.text, style, area);
+            return;
+        }
+        LineKind::Dimmed => match app.dimmed_color {
+            Some(color) => (scale, style.fg(color)),
+            None => (scale, style.add_modifier(Modifier::DIM)),
+        },
+        LineKind::Reduced => (scale.smaller().unwrap_or(scale), style),
+        LineKind::Normal => (scale, style),
+    };
+
+    // Smaller text sits vertically centered in the full-size line slot
+    let inset = (area.height.saturating_sub(get_line_height(scale))) / 2;
+    let area = Rect {
+        y: area.y + inset,
+        height: area.height - inset,
+        ..area
+    };
+
+    let alignment = match align {
+        Align::Left => Alignment::Left,
+        Align::Center => Alignment::Center,
+        Align::Right => Alignment::Right,
+        Align::Justify => {
+            render_justified(buf, &line.text, style, scale, area);
+            return;
+        }
+    };
+    render_big_text(buf, &line.text, style, scale, alignment, area);
+}
+
+/// Draws `text` as big text at the given scale and alignment.
+fn render_big_text(
+    buf: &mut Buffer,
+    text: &str,
+    style: Style,
+    scale: FontSize,
+    alignment: Alignment,
+    area: Rect,
+) {
+    let (pixel_size, upscale) = get_pixel_size(scale);
+    let big_text = BigText::builder()
+        .pixel_size(pixel_size)
+        .style(style)
+        .lines(vec![text.into()])
+        .alignment(alignment)
+        .build();
+
+    if upscale == 1 {
+        big_text.render(area, buf);
+    } else {
+        render_upscaled(big_text, buf, area, upscale);
+    }
+}
+
+/// Draws `text` word by word, widening the spaces between words so the line
+/// spans the whole width.
+fn render_justified(buf: &mut Buffer, text: &str, style: Style, scale: FontSize, area: Rect) {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let char_width = get_char_width(scale);
+    let letters: usize = words.iter().map(|word| word.chars().count()).sum();
+    let text_width = (letters as u16).saturating_mul(char_width);
+    let gaps = words.len().saturating_sub(1) as u16;
+    if gaps == 0 || text_width + gaps * char_width > area.width {
+        render_big_text(buf, text, style, scale, Alignment::Left, area);
+        return;
+    }
+
+    let spare = area.width - text_width;
+    let mut x = area.x;
+    for (i, word) in words.iter().enumerate() {
+        let width = word.chars().count() as u16 * char_width;
+        let word_area = Rect { x, width, ..area };
+        render_big_text(buf, word, style, scale, Alignment::Left, word_area);
+
+        // Spread the remainder over the first gaps
+        let gap = spare / gaps + u16::from((i as u16) < spare % gaps);
+        x += width + gap;
+    }
+}
 
-        if line_area.y + line_height <= area.y + area.height {
-            let big_text = BigText::builder()
-                .pixel_size(pixel_size)
-                .style(style)
-                .lines(vec![text.into()])
-                .centered()
-                .build();
+/// Renders big text at a fraction of `area`'s size and scales every cell up
+/// into a `factor` × `factor` block. Only used with full-cell pixels, whose
+/// blocks tile seamlessly.
+fn render_upscaled(big_text: BigText, buf: &mut Buffer, area: Rect, factor: u16) {
+    let small_area = Rect::new(
+        0,
+        0,
+        area.width.div_ceil(factor),
+        area.height.div_ceil(factor),
+    );
+    let mut small = Buffer::empty(small_area);
+    big_text.render(small_area, &mut small);
 
-            frame.render_widget(big_text, line_area);
+    for y in 0..area.height {
+        for x in 0..area.width {
+            let cell = &small[(x / factor, y / factor)];
+            if cell.symbol() != " " {
+                buf[(area.x + x, area.y + y)] = cell.clone();
+            }
         }
     }
 }
 
-fn render_status_bar(frame: &mut Frame, app: &App, area: Rect) {
+/// Draws a section marker (e.g. a scene heading) as a regular-sized divider
+/// across the middle of its line slot, in the highlight and guide colors.
+fn render_marker(buf: &mut Buffer, app: &App, text: &str, style: Style, area: Rect) {
+    let label = format!(" {} ", text);
+    let rule_len = (area.width as usize).saturating_sub(label.chars().count()) / 2;
+    let rule = "─".repeat(rule_len);
+    let style = style.add_modifier(Modifier::BOLD);
+    let label_style = app.highlight_color.map_or(style, |color| style.fg(color));
+    let rule_style = app.guide_color.map_or(style, |color| style.fg(color));
+
+    let marker_area = Rect {
+        y: area.y + area.height / 2,
+        height: 1,
+        ..area
+    };
+
+    let marker = Paragraph::new(Line::from(vec![
+        Span::styled(rule.clone(), rule_style),
+        Span::styled(label, label_style),
+        Span::styled(rule, rule_style),
+    ]))
+    .alignment(Alignment::Center);
+
+    marker.render(marker_area, buf);
+}
+
+/// Draws a message in a bordered box across the top of the text.
+fn render_overlay(buf: &mut Buffer, app: &App, message: &str, area: Rect) {
+    let width = area.width.saturating_sub(4).min(100);
+    let inner_width = width.saturating_sub(2).max(1) as usize;
+    let rows: usize = message
+        .lines()
+        .map(|line| line.width().div_ceil(inner_width).max(1))
+        .sum();
+    let overlay = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + 1.min(area.height),
+        width,
+        height: (rows as u16 + 2).min(area.height.saturating_sub(1)),
+    };
+
+    let style = Style::default().fg(app.text_color).bg(app.background_color);
+    let block = Block::bordered()
+        .border_style(style.fg(app.highlight_color.unwrap_or(app.text_color)))
+        .style(style);
+    Clear.render(overlay, buf);
+    Paragraph::new(message)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .render(overlay, buf);
+}
+
+fn render_status_bar(buf: &mut Buffer, app: &App, area: Rect) {
     let status_area = Rect {
         x: area.x,
         y: area.y + area.height.saturating_sub(1),
@@ -113,23 +436,41 @@ fn render_status_bar(frame: &mut Frame, app: &App, area: Rect) {
         height: 1,
     };
 
-    let status_style = Style::default()
-        .fg(app.text_color)
-        .bg(app.background_color)
-        .add_modifier(Modifier::DIM);
+    let status_style = match app.status_color {
+        Some(color) => Style::default().fg(color),
+        None => Style::default()
+            .fg(app.text_color)
+            .add_modifier(Modifier::DIM),
+    }
+    .bg(app.background_color);
 
     let pause_indicator = if app.paused { "[PAUSED] " } else { "" };
-    let total_lines = app.wrapped_lines.len();
+    let segment = app
+        .segment
+        .map(|segment| format!("{}/{}", segment.index + 1, segment.count));
+    let title = match (segment, app.title.as_deref()) {
+        (Some(segment), Some(title)) => format!("{} {} | ", segment, title),
+        (Some(segment), None) => format!("Segment {} | ", segment),
+        (None, Some(title)) => format!("{} | ", title),
+        (None, None) => String::new(),
+    };
+    let (total_lines, exact) = app.wrapped.len_estimate(&app.lines);
     let current_line = (app.scroll_offset as usize).min(total_lines);
+    let approx = if exact { "" } else { "~" };
+
+    let help = app
+        .notice
+        .as_deref()
+        .unwrap_or("[Space] Pause | [+/-] Speed | [?] Keys | [↑/↓] Scroll | [r] Reset | [q] Quit");
 
     let status_text = format!(
-        "{}Speed: {:.1} | {}/{} | [Space] Pause | [↑/↓] Scroll | [+/-] Speed | [r] Reset | [q] Quit",
-        pause_indicator, app.speed, current_line, total_lines
+        "{}{}Speed: {:.1} | {}/{}{} | {}",
+        pause_indicator, title, app.speed, current_line, approx, total_lines, help
     );
 
     let status = Paragraph::new(status_text)
         .style(status_style)
         .alignment(Alignment::Center);
 
-    frame.render_widget(status, status_area);
+    status.render(status_area, buf);
 }
@@ -0,0 +1,127 @@
+//! Noticing edits to the config and theme files while the prompter runs.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tui_prompter::clock::Clock;
+
+/// How often the files are looked at. Edits show up within this time.
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches files by polling their modification time and size, which the main
+/// loop does between frames like it polls for signals.
+pub struct FileWatcher {
+    files: Vec<(PathBuf, Option<(SystemTime, u64)>)>,
+    clock: Box<dyn Clock>,
+    last_check: Instant,
+}
+
+impl FileWatcher {
+    /// Starts watching `paths`, which don't need to exist yet.
+    pub fn new(paths: Vec<PathBuf>, clock: impl Clock + 'static) -> Self {
+        let mut watcher = Self {
+            files: Vec::new(),
+            last_check: clock.now(),
+            clock: Box::new(clock),
+        };
+        watcher.watch(paths);
+        watcher
+    }
+
+    /// Switches to watching `paths`, such as after the config names another
+    /// theme file. Files already watched keep their last seen state.
+    pub fn watch(&mut self, paths: Vec<PathBuf>) {
+        let mut files = std::mem::take(&mut self.files);
+        self.files = paths
+            .into_iter()
+            .map(|path| match files.iter().position(|(p, _)| *p == path) {
+                Some(i) => files.swap_remove(i),
+                None => {
+                    let state = file_state(&path);
+                    (path, state)
+                }
+            })
+            .collect();
+    }
+
+    /// Returns whether any file was changed, created or removed since the last
+    /// time this returned true.
+    pub fn changed(&mut self) -> bool {
+        let now = self.clock.now();
+        if now.duration_since(self.last_check) < CHECK_INTERVAL {
+            return false;
+        }
+        self.last_check = now;
+        let mut changed = false;
+        for (path, last) in &mut self.files {
+            let state = file_state(path);
+            if state != *last {
+                *last = state;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+fn file_state(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tui_prompter::clock::ManualClock;
+
+    #[test]
+    fn changes_are_noticed_once_per_check() {
+        let dir = std::env::temp_dir().join(format!("tp-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.toml");
+        let theme = dir.join("theme.toml");
+        fs::write(&config, "[scroll]\n").unwrap();
+
+        let clock = ManualClock::new();
+        let mut watcher = FileWatcher::new(vec![config.clone(), theme.clone()], clock.clone());
+        let mut check = || {
+            clock.advance(CHECK_INTERVAL);
+            watcher.changed()
+        };
+        let unchanged = check();
+        // Sizes differ, so the edits are seen even within the mtime resolution
+        fs::write(&config, "[scroll]\nspeed = 2.0\n").unwrap();
+        let edited = check();
+        let seen_once = check();
+        fs::write(&theme, "text = \"green\"\n").unwrap();
+        let created = check();
+        fs::remove_file(&config).unwrap();
+        let removed = check();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!unchanged);
+        assert!(edited);
+        assert!(!seen_once);
+        assert!(created);
+        assert!(removed);
+    }
+
+    #[test]
+    fn files_are_only_checked_every_interval() {
+        let dir = std::env::temp_dir().join(format!("tp-watch-interval-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.toml");
+
+        let clock = ManualClock::new();
+        let mut watcher = FileWatcher::new(vec![config.clone()], clock.clone());
+        fs::write(&config, "[scroll]\n").unwrap();
+        clock.advance(CHECK_INTERVAL / 2);
+        let early = watcher.changed();
+        clock.advance(CHECK_INTERVAL / 2);
+        let due = watcher.changed();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!early);
+        assert!(due);
+    }
+}
@@ -0,0 +1,353 @@
+//! Word wrapping for big text, which has no wrapping of its own.
+//!
+//! Scripts can be tens of thousands of lines long, so [`WrappedLines`] doesn't
+//! wrap a script up front. It counts how many wrapped lines each source line
+//! becomes as far as it has been asked to, and only builds the wrapped text of
+//! lines that are actually drawn.
+
+use crate::app::{LineKind, ScriptLine};
+use std::collections::HashMap;
+
+/// Number of source lines whose wrapped text is kept around. Far more than fit
+/// on any screen.
+const CAPACITY: usize = 256;
+
+/// Wraps text to fit within a given character width, preserving words when possible.
+///
+/// Runs of whitespace collapse to single spaces, and words longer than
+/// `max_chars` are split across lines. Returns no lines when `max_chars` is 0.
+pub fn wrap_text(text: &str, max_chars: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    wrap_pieces(text, max_chars, |pieces| lines.push(pieces.join(" ")));
+    lines
+}
+
+/// Returns how many lines `wrap_text` would produce, without building them.
+pub fn wrap_count(text: &str, max_chars: usize) -> usize {
+    let mut count = 0;
+    wrap_pieces(text, max_chars, |_| count += 1);
+    count
+}
+
+/// The wrapping algorithm behind `wrap_text` and `wrap_count`. Calls `emit`
+/// once per output line with the pieces to be joined by single spaces.
+fn wrap_pieces<'a>(text: &'a str, max_chars: usize, mut emit: impl FnMut(&[&'a str])) {
+    if max_chars == 0 {
+        return;
+    }
+    if text.split_whitespace().next().is_none() {
+        if !text.is_empty() {
+            emit(&[]);
+        }
+        return;
+    }
+
+    let mut current: Vec<&str> = Vec::new();
+    let mut current_len = 0;
+    let mut flush = |current: &mut Vec<&'a str>, current_len: &mut usize| {
+        emit(current);
+        current.clear();
+        *current_len = 0;
+    };
+
+    for word in text.split_whitespace() {
+        let word_len = word.chars().count();
+        if word_len > max_chars {
+            if !current.is_empty() {
+                flush(&mut current, &mut current_len);
+            }
+            // Split at character boundaries, not bytes
+            let mut remaining = word;
+            while let Some((split, _)) = remaining.char_indices().nth(max_chars) {
+                current.push(&remaining[..split]);
+                flush(&mut current, &mut current_len);
+                remaining = &remaining[split..];
+            }
+            if !remaining.is_empty() {
+                current.push(remaining);
+                current_len = remaining.chars().count();
+            }
+        } else if current.is_empty() {
+            current.push(word);
+            current_len = word_len;
+        } else if current_len + 1 + word_len <= max_chars {
+            current.push(word);
+            current_len += 1 + word_len;
+        } else {
+            flush(&mut current, &mut current_len);
+            current.push(word);
+            current_len = word_len;
+        }
+    }
+
+    if !current.is_empty() {
+        flush(&mut current, &mut current_len);
+    }
+}
+
+/// Wraps one script line. Blank lines stay a single empty line, and section
+/// markers are drawn as regular-sized text, so they are never wrapped.
+pub fn wrap_line(line: &ScriptLine, max_chars: usize) -> Vec<ScriptLine> {
+    if line.text.trim().is_empty() {
+        vec![ScriptLine::default()]
+    } else if line.kind == LineKind::Marker {
+        vec![line.clone()]
+    } else {
+        wrap_text(&line.text, max_chars)
+            .into_iter()
+            .map(|text| ScriptLine::new(text, line.kind).with_color(line.color))
+            .collect()
+    }
+}
+
+/// Returns how many lines `wrap_line` would produce.
+fn wrap_line_count(line: &ScriptLine, max_chars: usize) -> usize {
+    if line.text.trim().is_empty() || line.kind == LineKind::Marker {
+        1
+    } else {
+        wrap_count(&line.text, max_chars)
+    }
+}
+
+/// A script's lines wrapped to a width, computed lazily.
+///
+/// Wrapped lines are addressed by index as if the whole script had been
+/// wrapped. A prefix index maps each source line to the index of its first
+/// wrapped line; it is extended only as far as needed, so looking at the start
+/// of a long script or rewrapping it after a resize costs next to nothing. The
+/// wrapped text itself is only built for lines that are looked up.
+///
+/// `WrappedLines` doesn't own the source lines: every method that may need to
+/// wrap takes them, and they must be the same lines each time.
+#[derive(Debug, Clone)]
+pub struct WrappedLines {
+    /// Width to wrap to, or `None` to leave every line as it is
+    max_chars: Option<usize>,
+    /// Index of the first wrapped line of each indexed source line, plus the
+    /// total of the indexed lines at the end
+    starts: Vec<usize>,
+    /// Number of blank source lines before each indexed source line, plus the
+    /// total at the end, for paragraph spacing
+    blanks: Vec<usize>,
+    /// Wrapped text of recently looked up source lines
+    wrapped: HashMap<usize, Vec<ScriptLine>>,
+}
+
+impl Default for WrappedLines {
+    fn default() -> Self {
+        Self {
+            max_chars: None,
+            starts: vec![0],
+            blanks: vec![0],
+            wrapped: HashMap::new(),
+        }
+    }
+}
+
+impl WrappedLines {
+    /// Creates an empty index for wrapping to `max_chars` characters.
+    pub fn new(max_chars: usize) -> Self {
+        Self {
+            max_chars: Some(max_chars),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the width lines are wrapped to, or `None` if they aren't wrapped yet.
+    pub fn max_chars(&self) -> Option<usize> {
+        self.max_chars
+    }
+
+    fn count(&self, line: &ScriptLine) -> usize {
+        self.max_chars
+            .map_or(1, |max_chars| wrap_line_count(line, max_chars))
+    }
+
+    /// Returns the number of source lines indexed so far.
+    fn indexed(&self) -> usize {
+        self.starts.len() - 1
+    }
+
+    /// Returns the number of wrapped lines indexed so far.
+    fn indexed_len(&self) -> usize {
+        self.starts[self.indexed()]
+    }
+
+    /// Returns whether every source line has been indexed.
+    pub fn is_complete(&self, lines: &[ScriptLine]) -> bool {
+        self.indexed() >= lines.len()
+    }
+
+    /// Indexes the next source line.
+    fn index_next(&mut self, lines: &[ScriptLine]) {
+        let line = &lines[self.indexed()];
+        let start = self.indexed_len() + self.count(line);
+        let blanks = self.blanks[self.indexed()] + usize::from(line.text.trim().is_empty());
+        self.starts.push(start);
+        self.blanks.push(blanks);
+    }
+
+    /// Indexes up to `budget` more source lines, e.g. a chunk per frame so the
+    /// total becomes known without stalling. Returns whether indexing is complete.
+    pub fn index_more(&mut self, lines: &[ScriptLine], budget: usize) -> bool {
+        for _ in 0..budget {
+            if self.is_complete(lines) {
+                break;
+            }
+            self.index_next(lines);
+        }
+        self.is_complete(lines)
+    }
+
+    /// Indexes source lines until at least `len` wrapped lines are known or the
+    /// script ends, and returns the number of wrapped lines known.
+    pub fn len_up_to(&mut self, lines: &[ScriptLine], len: usize) -> usize {
+        while self.indexed_len() < len && !self.is_complete(lines) {
+            self.index_next(lines);
+        }
+        self.indexed_len()
+    }
+
+    /// Returns the total number of wrapped lines, indexing the rest of the script.
+    pub fn len(&mut self, lines: &[ScriptLine]) -> usize {
+        self.index_more(lines, usize::MAX);
+        self.indexed_len()
+    }
+
+    /// Returns whether there are no wrapped lines at all.
+    pub fn is_empty(&mut self, lines: &[ScriptLine]) -> bool {
+        self.len_up_to(lines, 1) == 0
+    }
+
+    /// Returns the total number of wrapped lines if every source line has been
+    /// indexed, or otherwise an estimate from the lines indexed so far.
+    pub fn len_estimate(&self, lines: &[ScriptLine]) -> (usize, bool) {
+        if self.is_complete(lines) {
+            return (self.indexed_len(), true);
+        }
+        let remaining = lines.len() - self.indexed();
+        let per_line = if self.indexed() == 0 {
+            1.0
+        } else {
+            self.indexed_len() as f64 / self.indexed() as f64
+        };
+        let estimate = self.indexed_len() + (remaining as f64 * per_line).round() as usize;
+        (estimate, false)
+    }
+
+    /// Returns the index of the source line that wrapped line `idx` belongs to.
+    pub fn source_line(&mut self, lines: &[ScriptLine], idx: usize) -> Option<usize> {
+        if idx >= self.len_up_to(lines, idx + 1) {
+            return None;
+        }
+        Some(self.starts.partition_point(|&start| start <= idx) - 1)
+    }
+
+    /// Returns the index of the first wrapped line of source line `line`.
+    pub fn first_wrapped(&mut self, lines: &[ScriptLine], line: usize) -> usize {
+        let missing = (line + 1).saturating_sub(self.indexed());
+        self.index_more(lines, missing);
+        self.starts[line.min(self.indexed())]
+    }
+
+    /// Returns how many paragraph breaks (blank source lines) come before
+    /// wrapped line `idx`. Past the end of the script, that is all of them.
+    pub fn blanks_before(&mut self, lines: &[ScriptLine], idx: usize) -> usize {
+        match self.source_line(lines, idx) {
+            Some(source) => self.blanks[source],
+            None => self.blanks[self.indexed()],
+        }
+    }
+
+    /// Returns wrapped line `idx`, wrapping its source line if needed.
+    pub fn get(&mut self, lines: &[ScriptLine], idx: usize) -> Option<&ScriptLine> {
+        let source = self.source_line(lines, idx)?;
+        let offset = idx - self.starts[source];
+
+        if !self.wrapped.contains_key(&source) && self.wrapped.len() >= CAPACITY {
+            self.wrapped.clear();
+        }
+        let max_chars = self.max_chars;
+        self.wrapped
+            .entry(source)
+            .or_insert_with(|| match max_chars {
+                Some(max_chars) => wrap_line(&lines[source], max_chars),
+                None => vec![lines[source].clone()],
+            })
+            .get(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script() -> Vec<ScriptLine> {
+        [
+            "The quick brown fox jumps over the lazy dog",
+            "",
+            "INT. HOUSE - NIGHT",
+            "supercalifragilisticexpialidocious is a long word",
+            "   ",
+            "short",
+            "Ünïcödé wörds like Donaudampfschifffahrtsgesellschaftskapitän wrap by character",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, text)| {
+            let kind = if i == 2 {
+                LineKind::Marker
+            } else {
+                LineKind::Normal
+            };
+            ScriptLine::new(*text, kind)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn lazy_lines_match_wrapping_everything() {
+        assert_eq!(wrap_text("ééééé", 3), ["ééé", "éé"]);
+        assert_eq!(wrap_count("ééééé", 3), 2);
+
+        let lines = script();
+        for max_chars in 1..=20 {
+            let eager: Vec<ScriptLine> = lines
+                .iter()
+                .flat_map(|line| wrap_line(line, max_chars))
+                .collect();
+
+            let mut wrapped = WrappedLines::new(max_chars);
+            // Look lines up out of order, before the total is known
+            for idx in (0..eager.len()).rev().chain(0..eager.len()) {
+                assert_eq!(wrapped.get(&lines, idx), Some(&eager[idx]));
+            }
+            assert_eq!(wrapped.get(&lines, eager.len()), None);
+            assert_eq!(wrapped.len(&lines), eager.len());
+        }
+    }
+
+    #[test]
+    fn indexing_stops_as_soon_as_enough_lines_are_known() {
+        let lines = vec![ScriptLine::new("one two three", LineKind::Normal); 1000];
+        let mut wrapped = WrappedLines::new(5);
+
+        assert_eq!(wrapped.len_up_to(&lines, 7), 9);
+        assert!(!wrapped.is_complete(&lines));
+        assert_eq!(wrapped.len_estimate(&lines), (3000, false));
+        assert_eq!(wrapped.first_wrapped(&lines, 10), 30);
+        assert_eq!(wrapped.source_line(&lines, 31), Some(10));
+    }
+
+    #[test]
+    fn blank_source_lines_are_counted_as_paragraph_breaks() {
+        let lines = script();
+        let mut wrapped = WrappedLines::new(20);
+        let blank = wrapped.first_wrapped(&lines, 4);
+
+        assert_eq!(wrapped.blanks_before(&lines, 0), 0);
+        assert_eq!(wrapped.blanks_before(&lines, blank), 1);
+        assert_eq!(wrapped.blanks_before(&lines, blank + 1), 2);
+        assert_eq!(wrapped.blanks_before(&lines, 1000), 2);
+    }
+}
@@ -0,0 +1,223 @@
+//! Golden snapshot tests for headless rendering.
+//!
+//! Each test renders a frame with `TestBackend` and compares it against a file
+//! in `tests/golden/`. Run with `UPDATE_GOLDEN=1` to rewrite the files after an
+//! intended rendering change, then review the diff.
+
+use std::fs;
+use std::path::PathBuf;
+use tui_prompter::App;
+use tui_prompter::config::{Align, Config, FontScale, FontSize};
+use tui_prompter::snapshot::{buffer_to_ansi, buffer_to_text, render_to_buffer};
+
+const SCRIPT: &str = "\
+Good evening and welcome.
+
+Tonight we look at the weather,
+the markets, and a story about a very determined cat.
+
+Back to you.";
+
+fn render(scale: FontSize, padding: u16, offset: f64, ansi: bool) -> String {
+    render_with(FontScale::Fixed(scale), padding, offset, ansi)
+}
+
+fn render_with(scale: FontScale, padding: u16, offset: f64, ansi: bool) -> String {
+    let mut config = Config::default();
+    config.display.font_scale = scale;
+    config.display.horizontal_padding = padding;
+    config.display.text_color = Some("green".to_string());
+    render_config(&config, offset, ansi)
+}
+
+fn render_config(config: &Config, offset: f64, ansi: bool) -> String {
+    let mut app = App::new(SCRIPT.to_string(), config).unwrap();
+    app.scroll_offset = offset;
+
+    let buffer = render_to_buffer(&mut app, 80, 24);
+    if ansi {
+        buffer_to_ansi(&buffer)
+    } else {
+        buffer_to_text(&buffer)
+    }
+}
+
+fn assert_golden(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(name);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("missing {}; run with UPDATE_GOLDEN=1", path.display()));
+    assert!(
+        expected == actual,
+        "{} does not match.\n--- expected\n{}\n--- actual\n{}",
+        name,
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn scale_1_padding_0() {
+    assert_golden(
+        "scale1_pad0.txt",
+        &render(FontSize::Quadrant, 0, 6.0, false),
+    );
+}
+
+#[test]
+fn scale_1_padding_10() {
+    assert_golden(
+        "scale1_pad10.txt",
+        &render(FontSize::Quadrant, 10, 6.0, false),
+    );
+}
+
+#[test]
+fn scale_1_padding_40() {
+    assert_golden(
+        "scale1_pad40.txt",
+        &render(FontSize::Quadrant, 40, 6.0, false),
+    );
+}
+
+#[test]
+fn scale_2_padding_0() {
+    assert_golden(
+        "scale2_pad0.txt",
+        &render(FontSize::HalfHeight, 0, 6.0, false),
+    );
+}
+
+#[test]
+fn scale_2_padding_10() {
+    assert_golden(
+        "scale2_pad10.txt",
+        &render(FontSize::HalfHeight, 10, 6.0, false),
+    );
+}
+
+#[test]
+fn scale_2_padding_40() {
+    assert_golden(
+        "scale2_pad40.txt",
+        &render(FontSize::HalfHeight, 40, 6.0, false),
+    );
+}
+
+#[test]
+fn scale_3_padding_0() {
+    assert_golden("scale3_pad0.txt", &render(FontSize::Full, 0, 6.0, false));
+}
+
+#[test]
+fn scale_3_padding_10() {
+    assert_golden("scale3_pad10.txt", &render(FontSize::Full, 10, 6.0, false));
+}
+
+#[test]
+fn scale_3_padding_40() {
+    assert_golden("scale3_pad40.txt", &render(FontSize::Full, 40, 6.0, false));
+}
+
+#[test]
+fn text_starts_below_the_screen() {
+    assert_golden(
+        "scale2_start.txt",
+        &render(FontSize::HalfHeight, 10, 0.0, false),
+    );
+}
+
+#[test]
+fn ansi_output_includes_colors() {
+    assert_golden(
+        "scale2_pad10.ansi",
+        &render(FontSize::HalfHeight, 10, 6.0, true),
+    );
+}
+
+#[test]
+fn size_sextant() {
+    assert_golden(
+        "sextant_pad10.txt",
+        &render(FontSize::Sextant, 10, 6.0, false),
+    );
+}
+
+#[test]
+fn size_third_height() {
+    assert_golden(
+        "third_height_pad10.txt",
+        &render(FontSize::ThirdHeight, 10, 6.0, false),
+    );
+}
+
+#[test]
+fn size_half_width() {
+    assert_golden(
+        "half_width_pad10.txt",
+        &render(FontSize::HalfWidth, 10, 6.0, false),
+    );
+}
+
+#[test]
+fn size_double() {
+    assert_golden(
+        "double_pad10.txt",
+        &render(FontSize::Double, 10, 12.0, false),
+    );
+}
+
+#[test]
+fn auto_size_fits_the_padded_width() {
+    assert_golden(
+        "auto_pad10.txt",
+        &render_with(FontScale::Auto, 10, 6.0, false),
+    );
+    assert_golden(
+        "auto_pad40.txt",
+        &render_with(FontScale::Auto, 40, 6.0, false),
+    );
+}
+
+#[test]
+fn auto_size_leaves_room_for_line_spacing() {
+    let mut config = Config::default();
+    config.display.font_scale = FontScale::Auto;
+    config.display.horizontal_padding = 0;
+    let render_short = |config: &Config| {
+        let mut app = App::new(SCRIPT.to_string(), config).unwrap();
+        app.scroll_offset = 9.0;
+        let text = buffer_to_text(&render_to_buffer(&mut app, 120, 10));
+        (app.font_scale, text)
+    };
+
+    let (unspaced, _) = render_short(&config);
+    config.display.line_spacing = 2;
+    let (spaced, text) = render_short(&config);
+    assert_eq!(unspaced, FontSize::Full);
+    assert_eq!(spaced, FontSize::HalfHeight);
+    assert_golden("auto_spacing.txt", &text);
+}
+
+#[test]
+fn alignment_and_spacing() {
+    let mut config = Config::default();
+    config.display.font_scale = FontScale::Fixed(FontSize::Quadrant);
+    config.display.line_spacing = 1;
+    config.display.paragraph_spacing = 2;
+
+    config.display.align = Align::Left;
+    assert_golden("left_spacing.txt", &render_config(&config, 6.0, false));
+    config.display.align = Align::Right;
+    assert_golden("right_spacing.txt", &render_config(&config, 6.0, false));
+    config.display.align = Align::Justify;
+    assert_golden("justify_spacing.txt", &render_config(&config, 6.0, false));
+}